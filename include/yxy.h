@@ -3,6 +3,13 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Authenticated client, opaque to C
+ *
+ * Reauthorize by the uid on expiry and retry the request once.
+ */
+typedef struct authenticated_client authenticated_client;
+
 typedef struct ele_result {
   float total_surplus;
   float total_amount;
@@ -14,6 +21,99 @@ typedef struct ele_result {
   char *room_status;
 } ele_result;
 
+/**
+ * Bind info
+ * -----------
+ * Room bound to the account, see `query_bind`.
+ * - `is_allow_change: c_int`: 0: not allowed, 1: allowed to change the bind
+ */
+typedef struct bind_info {
+  char *id;
+  char *school_code;
+  char *school_name;
+  char *job_no;
+  char *user_name;
+  char *bind_type_str;
+  char *area_id;
+  char *area_name;
+  char *building_code;
+  char *building_name;
+  char *floor_code;
+  char *floor_name;
+  char *room_code;
+  char *room_name;
+  char *create_time;
+  int is_allow_change;
+} bind_info;
+
+/**
+ * Bind query result
+ * -----------
+ * - `list: *mut bind_info`: array of `len` bind infos, the last one is queried by `query_ele`
+ */
+typedef struct bind_result {
+  struct bind_info *list;
+  uintptr_t len;
+} bind_result;
+
+/**
+ * Electricity surplus of a meter
+ */
+typedef struct el_surplus {
+  float surplus;
+  float amount;
+  float subsidy;
+  float subsidy_amount;
+  float total_surplus;
+  char *mdtype;
+  char *mdname;
+  char *room_status;
+} el_surplus;
+
+/**
+ * Top-up type of the room
+ * -----------
+ * - `cztype: *mut c_char`: type code to recharge by
+ */
+typedef struct el_top_up_type {
+  char *mdname;
+  char *cztype;
+} el_top_up_type;
+
+/**
+ * Electricity info
+ * -----------
+ * Full electricity info of the room, unlike the flattened `ele_result`.
+ * - `surplus_list: *mut el_surplus`: array of `surplus_len` surplus
+ * - `top_up_type_list: *mut el_top_up_type`: array of `top_up_type_len` top-up types
+ */
+typedef struct ele_info {
+  char *school_code;
+  char *area_id;
+  char *building_code;
+  char *floor_code;
+  char *room_code;
+  char *display_room_name;
+  float soc;
+  float total_soc_amount;
+  int is_allow_change;
+  int show_type;
+  int record_show;
+  int style;
+  struct el_surplus *surplus_list;
+  uintptr_t surplus_len;
+  struct el_top_up_type *top_up_type_list;
+  uintptr_t top_up_type_len;
+} ele_info;
+
+/**
+ * Session refreshed callback
+ * -----------
+ * - `session: *const c_char`: new session c-string, only valid during the call
+ * - `user_data: *mut c_void`: pointer passed to `new_client`
+ */
+typedef void (*session_callback)(const char *session, void *user_data);
+
 typedef struct login_handle {
   char *phone_num;
   char *device_id;
@@ -42,6 +142,42 @@ typedef struct login_result {
   int bind_card_status;
 } login_result;
 
+/**
+ * Error code of the last failed call -- C Bind
+ * -----------
+ * Kept per thread, cleared on the next call.
+ *
+ * # Returns
+ * - `c_int`: error code, 0 if the last call succeeded
+ *
+ * # Error codes
+ * - `101`: Unhandled error
+ * - `201`: Authentication expired
+ * - `202`: No bind info
+ * - `203`: Initialization handler error
+ * - `204`: Bad phone number
+ * - `205`: Limit of SMS verification code sent
+ * - `206`: Bad(Wrong) verification code
+ * - `207`: Get captcha image failed
+ * - `208`: Invalid argument, null pointer or non UTF-8 string
+ * - `209`: Request rejected by the platform API
+ * - `210`: App login token revoked, login again
+ * - `211`: Bad phone number or password
+ */
+int yxy_last_error_code(void);
+
+/**
+ * Error message of the last failed call -- C Bind
+ * -----------
+ * The caller is responsible for using `free_c_string` to deallocate the string.
+ *
+ * Reading the last error does not clear it.
+ *
+ * # Returns
+ * - `*mut c_char`: message c-string, UTF-8. Return nullptr if the last call succeeded.
+ */
+char *yxy_last_error_message(void);
+
 /**
  * Authorization -- C Bind
  * ----------
@@ -69,6 +205,8 @@ char *auth(const char *uid);
  * # Errors (status codes)
  * - `201`: Auth expired
  * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
  * - `101`: Other error
  */
 int query_ele(const char *session, struct ele_result **result);
@@ -76,10 +214,165 @@ int query_ele(const char *session, struct ele_result **result);
 /**
  * Free ele_result
  * -----------
- * Deallocate the struct to avoid memory leak.
+ * Deallocate the struct to avoid memory leak. Nullptr is ignored.
  */
 void free_ele_result(struct ele_result *p);
 
+/**
+ * Query bind infos -- C Bind
+ * -----------
+ * Query all rooms bound to the account.
+ *
+ * After calling this function,
+ * the caller is responsible for using `free_bind_result` to deallocate the memory.
+ *
+ * # Inputs
+ * - `session: *const c_char`: session c-string
+ * - `result: *mut *mut bind_result`: second-level pointer for return pointer of `bind_result` struct
+ *
+ * # Returns
+ * - `c_int`: 0 on success, otherwise error code
+ *
+ * # Errors (status codes)
+ * - `201`: Auth expired
+ * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
+ * - `101`: Other error
+ */
+int query_bind(const char *session, struct bind_result **result);
+
+/**
+ * Free bind_result
+ * -----------
+ * Deallocate the struct with its bind infos to avoid memory leak. Nullptr is ignored.
+ */
+void free_bind_result(struct bind_result *p);
+
+/**
+ * Query full electricity info -- C Bind
+ * -----------
+ * Like `query_ele`, with all surplus and top-up types of the room.
+ *
+ * After calling this function,
+ * the caller is responsible for using `free_ele_info` to deallocate the memory.
+ *
+ * # Inputs
+ * - `session: *const c_char`: session c-string
+ * - `result: *mut *mut ele_info`: second-level pointer for return pointer of `ele_info` struct
+ *
+ * # Returns
+ * - `c_int`: 0 on success, otherwise error code
+ *
+ * # Errors (status codes)
+ * - `201`: Auth expired
+ * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
+ * - `101`: Other error
+ */
+int query_ele_info(const char *session, struct ele_info **result);
+
+/**
+ * Free ele_info
+ * -----------
+ * Deallocate the struct with its arrays to avoid memory leak. Nullptr is ignored.
+ */
+void free_ele_info(struct ele_info *p);
+
+/**
+ * New authenticated client -- C Bind
+ * -----------
+ * The caller is responsible for using `free_client` to deallocate the client.
+ *
+ * # Inputs
+ * - `uid: *const c_char`: uid c-string
+ * - `session: *const c_char`: cached session c-string.
+ *   If `NULL`, authorize by the uid now.
+ * - `callback: session_callback`: called on the session refreshed, e.g. to persist it. Nullable.
+ * - `user_data: *mut c_void`: passed to the callback
+ * - `result: *mut *mut authenticated_client`: second-level pointer for return pointer of the client
+ *
+ * # Returns
+ * - `c_int`: 0 on success, otherwise error code
+ *
+ * # Errors
+ * - `208`: Invalid argument
+ * - `209`: Authorization rejected by the platform API
+ * - `101`: Other error
+ */
+int new_client(const char *uid,
+               const char *session,
+               session_callback callback,
+               void *user_data,
+               struct authenticated_client **result);
+
+/**
+ * Query electricity by client -- C Bind
+ * -----------
+ * Like `query_ele`, reauthorize and retry once if the session expired.
+ *
+ * # Inputs
+ * - `client: *mut authenticated_client`: client created by `new_client`
+ * - `result: *mut *mut ele_result`: second-level pointer for return pointer of `ele_result` struct
+ *
+ * # Returns
+ * - `c_int`: 0 on success, otherwise error code
+ *
+ * # Errors (status codes)
+ * - `201`: Auth expired after retry
+ * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
+ * - `101`: Other error
+ */
+int client_query_ele(struct authenticated_client *client, struct ele_result **result);
+
+/**
+ * Query bind infos by client -- C Bind
+ * -----------
+ * Like `query_bind`, reauthorize and retry once if the session expired.
+ *
+ * # Errors (status codes)
+ * - `201`: Auth expired after retry
+ * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
+ * - `101`: Other error
+ */
+int client_query_bind(struct authenticated_client *client, struct bind_result **result);
+
+/**
+ * Query full electricity info by client -- C Bind
+ * -----------
+ * Like `query_ele_info`, reauthorize and retry once if the session expired.
+ *
+ * # Errors (status codes)
+ * - `201`: Auth expired after retry
+ * - `202`: No bind info
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
+ * - `101`: Other error
+ */
+int client_query_ele_info(struct authenticated_client *client, struct ele_info **result);
+
+/**
+ * Current session of client -- C Bind
+ * -----------
+ * The caller is responsible for using `free_c_string` to deallocate the string.
+ *
+ * # Returns
+ * - `*mut c_char`: session c-string. Return nullptr on error.
+ */
+char *client_session(const struct authenticated_client *client);
+
+/**
+ * Free authenticated_client
+ * -----------
+ * Deallocate the client to avoid memory leak. Nullptr is ignored.
+ */
+void free_client(struct authenticated_client *p);
+
 /**
  * Generate random device id -- C Bind
  * -----------
@@ -89,7 +382,8 @@ void free_ele_result(struct ele_result *p);
  * the string in the structure.
  *
  * # Inputs
- * - `handle: *const login_handle`: Pointer of Login handle
+ * - `handle: *const login_handle`: Pointer of Login handle.
+ *   Left untouched on error, see `yxy_last_error_code`.
  */
 void gen_device_id(struct login_handle *handler);
 
@@ -107,6 +401,8 @@ void gen_device_id(struct login_handle *handler);
  *
  * # Errors
  * - `203`: Initialize login handler failed
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
  * - `101: Other errors
  */
 int get_security_token(const struct login_handle *handle,
@@ -115,14 +411,14 @@ int get_security_token(const struct login_handle *handle,
 /**
  * Free security_token_result
  * -----------
- * Deallocate the struct to avoid memory leak.
+ * Deallocate the struct to avoid memory leak. Nullptr is ignored.
  */
 void free_security_token_result(struct security_token_result *p);
 
 /**
  * Free c_string
  * -----------
- * Deallocate c_string to avoid memory leak.
+ * Deallocate c_string to avoid memory leak. Nullptr is ignored.
  */
 void free_c_string(char *c_string);
 
@@ -142,11 +438,10 @@ void free_c_string(char *c_string);
  * - `result: *mut *mut c_char`: captcha image in base64
  *
  * # Errors
+ * - `203`: Initialize login handler failed
  * - `207`: Get captcha image failed
+ * - `208`: Invalid argument
  * - `101: Other errors
- *
- * # Usage
- *
  */
 int get_captcha_image(const struct login_handle *handle, const char *security_token, char **result);
 
@@ -157,13 +452,15 @@ int get_captcha_image(const struct login_handle *handle, const char *security_to
  * - `handle: *const login_handle`: Pointer of Login handle
  * - `security_token: *const c_char`: c-string of security token
  * - `captcha: *const c_char`: c-string of captcha.
- * If captcha input `NULL`, it means no captcha is required.
+ *   If captcha input `NULL`, it means no captcha is required.
  * # Returns
  * - `c_int`: `0` on success, `1` on user is not exist(registered), otherwise error code
  * # Errors
  * - `203`: Initialize login handler failed
  * - `204`: Bad phone number
  * - `205`: Limit of SMS verification code sent
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
  * - `101: Other errors
  */
 int send_verification_code(const struct login_handle *handle,
@@ -183,6 +480,8 @@ int send_verification_code(const struct login_handle *handle,
  * # Errors
  * - `203`: Initialize login handler failed
  * - `206`: Bad(Wrong) verification code
+ * - `208`: Invalid argument
+ * - `209`: Request rejected by the platform API
  * - `101: Other errors
  */
 int do_login(const struct login_handle *handle,
@@ -192,6 +491,53 @@ int do_login(const struct login_handle *handle,
 /**
  * Free login_result
  * ---------
- * Deallocate the struct to avoid memory leak.
+ * Deallocate the struct to avoid memory leak. Nullptr is ignored.
  */
 void free_login_result(struct login_result *p);
+
+/**
+ * Call by method name and JSON arguments -- C Bind
+ * -----------
+ * JSON variant of the C Binds, for dynamic-language bindings.
+ * Arguments and results are the serde JSON of the Rust types, in camelCase.
+ *
+ * The caller is responsible for using `free_c_string` to deallocate the result.
+ *
+ * # Inputs
+ * - `method: *const c_char`: method name c-string
+ * - `json_args: *const c_char`: JSON object c-string of arguments, UTF-8. `NULL` for no arguments.
+ *   An optional `endpoints` object `{auth, application, app, serverChan}` replaces the service base URLs,
+ *   each defaults to the production platform. Unknown keys of arguments are rejected.
+ *
+ * # Methods
+ * - `auth`: `{uid}` -> `{session, user}`
+ * - `query_bind`: `{session}` -> list of bind info
+ * - `query_ele`: `{session}` -> electricity info
+ * - `gen_device_id`: no arguments -> device id
+ * - `get_security_token`: `{phoneNum, deviceId}` -> `{level, securityToken}`
+ * - `get_captcha_image`: `{phoneNum, deviceId, securityToken}` -> captcha image in base64
+ * - `send_verification_code`: `{phoneNum, deviceId, securityToken, captcha?}`
+ *   -> `false` on user is not exist(registered)
+ * - `do_login`: `{phoneNum, deviceId, code}` -> login response
+ * - `do_login_by_password`: `{phoneNum, deviceId, password}` -> login response
+ * - `do_silent_login`: `{phoneNum, deviceId, token}` -> login response
+ *
+ * # Returns
+ * - `*mut c_char`: JSON c-string, `{"code": 0, "data": ...}` on success,
+ *   otherwise `{"code": <error code>, "message": "..."}`. Error codes are the same as the C Binds.
+ *
+ * # Errors
+ * - `201`: Auth expired, of `query_bind` and `query_ele`
+ * - `202`: No bind info, of `query_bind` and `query_ele`
+ * - `203`: Initialize login handler failed, of the login methods
+ * - `204`: Bad phone number, of `send_verification_code`
+ * - `205`: Limit of SMS verification code sent, of `send_verification_code`
+ * - `206`: Bad(Wrong) verification code, of `do_login`
+ * - `207`: Get captcha image failed, of `get_captcha_image`
+ * - `208`: Invalid argument, unknown method or bad arguments
+ * - `209`: Request rejected by the platform API
+ * - `210`: App login token revoked, of `do_silent_login`
+ * - `211`: Bad phone number or password, of `do_login_by_password`
+ * - `101`: Other error
+ */
+char *yxy_call(const char *method, const char *json_args);
@@ -31,6 +31,105 @@ pub enum Commands {
 
         /// Argument
         arg: String,
+
+        /// `ele`: bound room to query, in `<area>-<building>-<floor>-<room>` format,
+        /// defaults to the last bound one.
+        /// `room`: room code
+        #[clap(short, long)]
+        room: Option<String>,
+
+        /// `room`: area id
+        #[clap(long)]
+        area: Option<String>,
+
+        /// `room`: building code
+        #[clap(long)]
+        building: Option<String>,
+
+        /// `room`: floor code
+        #[clap(long)]
+        floor: Option<String>,
+
+        /// `uid`: login by password read from stdin, instead of SMS verification code
+        #[clap(long)]
+        password: bool,
+    },
+
+    /// Poll electricity by config on a schedule,
+    /// notify on state transitions only
+    Watch {
+        /// Polling interval, like `30m`, `1h30m`, overrides the config
+        #[clap(short, long)]
+        interval: Option<String>,
+
+        /// Stop after polling the number of times
+        #[clap(long)]
+        times: Option<u32>,
+    },
+
+    /// Recharge electricity of the account's room by config
+    Recharge {
+        /// Amount in yuan
+        #[clap(short, long)]
+        amount: f32,
+
+        /// Top-up type, by `cztype` or name, defaults to the first listed one
+        #[clap(short = 't', long = "type")]
+        top_up_type: Option<String>,
+
+        /// Account name or uid, defaults to the first one
+        #[clap(long)]
+        account: Option<String>,
+
+        /// Show the order to submit without paying
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Save secrets into the encrypted store of config,
+    /// the passphrase is read from `YXY_PASSPHRASE` env or stdin
+    Store {
+        /// UID of the top-level account
+        #[clap(long)]
+        uid: Option<String>,
+    },
+
+    /// Login and save the app login profile by config,
+    /// silently by the saved token if valid, otherwise by SMS verification code
+    #[clap(args_conflicts_with_subcommands = true)]
+    Login {
+        /// Phone number, defaults to the one of saved profile
+        phone: Option<String>,
+
+        /// Two-phase login, for the verification code arriving asynchronously
+        #[clap(subcommand)]
+        step: Option<LoginStep>,
+    },
+
+    /// Electricity history recorded by config
+    History {
+        /// Only show the room, in `<area>-<building>-<floor>-<room>` format
+        #[clap(short, long)]
+        room: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LoginStep {
+    /// Send the SMS verification code, save the pending login for `finish`
+    Start {
+        /// Phone number
+        phone: String,
+
+        /// Answer of the image captcha, continuing the pending login
+        #[clap(long)]
+        captcha: Option<String>,
+    },
+
+    /// Login by the SMS verification code of the pending login, save the profile
+    Finish {
+        /// SMS verification code
+        code: String,
     },
 }
 
@@ -43,4 +142,13 @@ pub enum Query {
     /// Query UID by phone number
     #[clap(name = "uid")]
     Uid,
+
+    /// Query bound rooms by UID
+    #[clap(name = "bind")]
+    Bind,
+
+    /// Query electricity of any room by UID,
+    /// list the next level if the room is not fully specified
+    #[clap(name = "room")]
+    Room,
 }
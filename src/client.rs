@@ -0,0 +1,111 @@
+//! Authenticated client
+//! ------------
+//! Owns the uid and session, reauthorizes on [`Error::AuthExpired`]
+//! and retries the request once.
+use crate::error::Error;
+use crate::req::app::{BindInfo, ElectricityInfo, RechargeInfo, RechargeOrder, RoomInfo};
+use crate::req::{url::Endpoints, Handler};
+use crate::session::Session;
+
+/// Callback on the session refreshed, e.g. to persist it
+pub type RefreshCallback = Box<dyn FnMut(&Session) + Send>;
+
+/// Self-healing client of the application service
+pub struct AuthenticatedClient {
+    session: Session,
+    endpoints: Endpoints,
+    handler: Handler,
+    on_refresh: Option<RefreshCallback>,
+}
+
+impl std::fmt::Debug for AuthenticatedClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthenticatedClient")
+            .field("session", &self.session)
+            .field("endpoints", &self.endpoints)
+            .finish()
+    }
+}
+
+impl AuthenticatedClient {
+    /// Authorize by uid
+    pub fn new(uid: &str) -> Result<Self, Error> {
+        Self::with_endpoints(uid, Endpoints::default())
+    }
+
+    /// Authorize by uid with the given endpoints
+    pub fn with_endpoints(uid: &str, endpoints: Endpoints) -> Result<Self, Error> {
+        let session = Session::auth(uid, &endpoints)?;
+        Self::with_session(session, endpoints)
+    }
+
+    /// Use an existing (e.g. cached) session, reauthorize only on expiry
+    pub fn with_session(session: Session, endpoints: Endpoints) -> Result<Self, Error> {
+        Ok(Self {
+            handler: session.handler(endpoints.clone())?,
+            session,
+            endpoints,
+            on_refresh: None,
+        })
+    }
+
+    /// Set the callback on the session refreshed
+    pub fn on_refresh<F: FnMut(&Session) + Send + 'static>(mut self, f: F) -> Self {
+        self.on_refresh = Some(Box::new(f));
+        self
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    /// Reauthorize by the uid and notify the callback
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        self.session.refresh(&self.endpoints)?;
+        self.handler = self.session.handler(self.endpoints.clone())?;
+        if let Some(f) = &mut self.on_refresh {
+            f(&self.session);
+        }
+        Ok(())
+    }
+
+    /// Call with the handler, reauthorize and retry once on [`Error::AuthExpired`]
+    pub fn call<T, F: Fn(&Handler) -> Result<T, Error>>(&mut self, f: F) -> Result<T, Error> {
+        let result = match f(&self.handler) {
+            Err(Error::AuthExpired) => {
+                self.refresh()?;
+                f(&self.handler)
+            }
+            v => v,
+        };
+        if result.is_ok() {
+            self.session.touch();
+        }
+        result
+    }
+
+    pub fn query_bind(&mut self) -> Result<BindInfo, Error> {
+        self.call(|h| h.query_bind())
+    }
+
+    pub fn query_binds(&mut self) -> Result<Vec<BindInfo>, Error> {
+        self.call(|h| h.query_binds())
+    }
+
+    pub fn query_electricity(&mut self, info: RoomInfo) -> Result<ElectricityInfo, Error> {
+        self.call(|h| h.query_electricity(info))
+    }
+
+    /// Query electricity of the bound room, see [`crate::query_ele`]
+    pub fn query_ele(&mut self) -> Result<ElectricityInfo, Error> {
+        self.call(|h| h.query_electricity(h.query_bind()?.room_info()))
+    }
+
+    pub fn recharge(&mut self, info: &RechargeInfo) -> Result<RechargeOrder, Error> {
+        self.call(|h| h.recharge(info))
+    }
+}
@@ -1,29 +1,162 @@
 use serde::Deserialize;
 use std::error::Error;
 
+use yxy::history::Forecast;
+use yxy::req::url::Endpoints;
+
 /// Configuration file
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    pub uid: Option<String>,
+    pub cookie_file: Option<String>,
+    /// Bound room to query, see [`Account::room`]
+    pub room: Option<String>,
+    pub server_chan: Option<ServerChan>,
+    /// Send real recharge orders, see [`yxy::req::Handler::recharge`]
+    #[serde(default)]
+    pub allow_unverified_recharge: bool,
+    /// Automatic recharge rule of the top-level account
+    pub auto_recharge: Option<AutoRecharge>,
+    /// Additional accounts
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    /// Electricity history store, in JSON Lines format
+    pub history_file: Option<String>,
+    /// Encrypted store of uid and sessions, instead of plaintext `uid` and `cookie_file`
+    pub store_file: Option<String>,
+    /// App login profile saved by `yxy login`, kept in `store_file` if configured
+    pub profile_file: Option<String>,
+    /// Pending login of `yxy login start`, defaults to `./login_state.json`
+    pub login_state_file: Option<String>,
+    /// External captcha solver, program & arguments reading the image from stdin,
+    /// see [`yxy::req::captcha::CommandSolver`]
+    pub captcha_command: Option<Vec<String>>,
+    /// Custom service base URLs, defaults to the production platform
+    pub endpoints: Option<Endpoints>,
+    pub watch: Option<Watch>,
+}
+
+/// Account to query
+#[derive(Debug, Clone, Deserialize)]
+pub struct Account {
+    /// Display name, defaults to uid
+    pub name: Option<String>,
     pub uid: String,
     pub cookie_file: Option<String>,
+    /// Bound room to query, in `<area>-<building>-<floor>-<room>` format,
+    /// defaults to the last bound one
+    pub room: Option<String>,
+    /// Defaults to the top-level one
     pub server_chan: Option<ServerChan>,
+    /// Not inherited from the top-level one
+    pub auto_recharge: Option<AutoRecharge>,
+    /// Encrypted store of the config
+    #[serde(skip)]
+    pub store_file: Option<String>,
+}
+
+impl Account {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.uid)
+    }
 }
 
 #[derive(Debug, Deserialize)]
+pub struct Watch {
+    /// Polling interval, like `30m`, `1h30m`
+    pub interval: String,
+}
+
+/// Recharge `amount` yuan when surplus is less than `threshold` kW·h,
+/// at most `monthly_limit` yuan per month
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoRecharge {
+    pub threshold: f32,
+    pub amount: f32,
+    pub monthly_limit: f32,
+    /// By `cztype` or name, defaults to the first listed one
+    pub top_up_type: Option<String>,
+    /// Ledger of recharges performed, in JSON Lines format
+    pub ledger_file: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct ServerChan {
     pub key: String,
     pub title: String,
-    pub warning_threshold: f32,
+    /// Warn when surplus is less than the kW·h
+    pub warning_threshold: Option<f32>,
+    /// Warn when the forecast days remaining is less than the value
+    pub warning_days: Option<f32>,
     pub warning_title: String,
     pub log_level: u8,
 }
 
+impl ServerChan {
+    /// Whether to send warning message
+    pub fn is_warning(&self, soc: f32, forecast: Option<&Forecast>) -> bool {
+        if let Some(threshold) = self.warning_threshold {
+            if soc < threshold {
+                return true;
+            }
+        }
+        match (self.warning_days, forecast) {
+            (Some(days), Some(f)) => f.days < days,
+            _ => false,
+        }
+    }
+}
+
 impl Config {
     pub fn parse(path: &str) -> Result<Self, Box<dyn Error>> {
         let config_f = std::fs::File::open(path)?;
 
         let config: Config = serde_yaml::from_reader(config_f)?;
+        if config.uid.is_none()
+            && config.accounts.is_empty()
+            && config.store_file.is_none()
+            && config.profile_file.is_none()
+        {
+            return Err("No account configured, `uid` or `accounts` required".into());
+        }
 
         Ok(config)
     }
+
+    /// All accounts, the top-level one first
+    pub fn accounts(&self) -> Vec<Account> {
+        let mut result = Vec::new();
+        if let Some(uid) = &self.uid {
+            result.push(Account {
+                name: None,
+                uid: uid.clone(),
+                cookie_file: self.cookie_file.clone(),
+                room: self.room.clone(),
+                server_chan: self.server_chan.clone(),
+                auto_recharge: self.auto_recharge.clone(),
+                store_file: self.store_file.clone(),
+            });
+        }
+        for account in &self.accounts {
+            let mut account = account.clone();
+            if account.server_chan.is_none() {
+                account.server_chan = self.server_chan.clone();
+            }
+            account.store_file = self.store_file.clone();
+            result.push(account);
+        }
+
+        result
+    }
+
+    /// Account by name or uid, defaults to the first one
+    pub fn account(&self, name: Option<&str>) -> Option<Account> {
+        let accounts = self.accounts();
+        match name {
+            Some(name) => accounts
+                .into_iter()
+                .find(|a| a.label() == name || a.uid == name),
+            None => accounts.into_iter().next(),
+        }
+    }
 }
@@ -5,6 +5,15 @@ use Error::*;
 pub enum Error {
     IO(std::io::Error),
     Runtime(String),
+    /// Request failure reported by the platform
+    Api {
+        /// Path of the endpoint
+        endpoint: String,
+        /// Platform status code, or HTTP status code on bad HTTP status
+        status_code: i32,
+        message: String,
+        raw_body: String,
+    },
     Auth(String),
     AuthExpired,
     Request(reqwest::Error),
@@ -14,6 +23,8 @@ pub enum Error {
     VerificationLimit,
     BadPhoneNumber,
     BadVerificationCode,
+    BadPassword,
+    TokenRevoked,
     RsaPkcs(rsa::pkcs8::spki::Error),
     Decode(std::string::FromUtf8Error),
     Base64Decode(base64::DecodeError),
@@ -25,6 +36,16 @@ impl std::fmt::Display for Error {
         match self {
             IO(e) => write!(f, "IO error: {}", e),
             Runtime(e) => write!(f, "Runtime error: {}", e),
+            Api {
+                endpoint,
+                status_code,
+                message,
+                ..
+            } => write!(
+                f,
+                "API error of {}: {{code: {}, message: {}}}",
+                endpoint, status_code, message
+            ),
             Auth(e) => write!(f, "Authorization error: {}", e),
             Request(e) => write!(f, "Request error: {}", e),
             EmptyResp => write!(f, "Get empty response"),
@@ -38,11 +59,43 @@ impl std::fmt::Display for Error {
             VerificationLimit => write!(f, "Verification limited, maybe too many requests"),
             BadPhoneNumber => write!(f, "Bad phone number"),
             BadVerificationCode => write!(f, "Bad verification code"),
+            BadPassword => write!(f, "Bad phone number or password"),
+            TokenRevoked => write!(f, "App login token revoked, login again"),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IO(e) => Some(e),
+            Request(e) => Some(e),
+            Rsa(e) => Some(e),
+            RsaPkcs(e) => Some(e),
+            Decode(e) => Some(e),
+            Base64Decode(e) => Some(e),
+            Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Failure of the endpoint reported by the platform
+    pub(crate) fn api<S: Into<String>>(
+        endpoint: &str,
+        status_code: i32,
+        message: S,
+        raw_body: &str,
+    ) -> Self {
+        Api {
+            endpoint: endpoint.to_string(),
+            status_code,
+            message: message.into(),
+            raw_body: raw_body.to_string(),
+        }
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
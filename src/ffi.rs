@@ -10,7 +10,9 @@
 //!     char uid[] = "123456789\0";
 //!     char *session = auth(uid);
 //!     if (session == NULL) {
-//!       printf("auth error\n");
+//!       char *msg = yxy_last_error_message();
+//!       printf("auth error %d: %s\n", yxy_last_error_code(), msg);
+//!       free_c_string(msg);
 //!       return;
 //!     } else {
 //!       printf("session: %s\n", session);
@@ -35,6 +37,29 @@
 //! }
 //! ```
 //!
+//! ## Bound rooms and full electricity info
+//! ```c
+//! void query_all(const char *session) {
+//!     bind_result *binds;
+//!     if (query_bind(session, &binds) == 0) {
+//!       for (uintptr_t i = 0; i < binds->len; i++) {
+//!         printf("%s: %s-%s-%s-%s\n", binds->list[i].room_name, binds->list[i].area_id,
+//!                binds->list[i].building_code, binds->list[i].floor_code,
+//!                binds->list[i].room_code);
+//!       }
+//!       free_bind_result(binds);
+//!     }
+//!
+//!     ele_info *e;
+//!     if (query_ele_info(session, &e) == 0) {
+//!       for (uintptr_t i = 0; i < e->surplus_len; i++) {
+//!         printf("%s: %f\n", e->surplus_list[i].mdname, e->surplus_list[i].surplus);
+//!       }
+//!       free_ele_info(e);
+//!     }
+//! }
+//! ```
+//!
 //! ## App login (query uid)
 //! ```c
 //! void login() {
@@ -98,6 +123,18 @@
 //! }
 //! ```
 //!
+//! ## JSON call
+//! ```c
+//! void query_json(const char *session) {
+//!     char args[256];
+//!     snprintf(args, sizeof(args), "{\"session\": \"%s\"}", session);
+//!
+//!     char *resp = yxy_call("query_ele", args);
+//!     printf("%s\n", resp); // {"code":0,"data":{...}}
+//!     free_c_string(resp);
+//! }
+//! ```
+//!
 //! # Error code reference
 //! - `0`: Success
 //! - `101`: Unhandled error
@@ -108,12 +145,194 @@
 //! - `205`: Limit of SMS verification code sent
 //! - `206`: Bad(Wrong) verification code
 //! - `207`: Get captcha image failed
+//! - `208`: Invalid argument, null pointer or non UTF-8 string
+//! - `209`: Request rejected by the platform API
+//! - `210`: App login token revoked, login again
+//! - `211`: Bad phone number or password
+//!
+//! # Last error
+//! Calls never panic across the boundary. On failure, the error code and message
+//! are kept per thread until the next call, see `yxy_last_error_code` and `yxy_last_error_message`.
+//!
+//! ## Self-healing client
+//! ```c
+//! void on_refresh(const char *session, void *user_data) {
+//!     printf("new session: %s\n", session); // persist it
+//! }
+//!
+//! void query_by_client(const char *cached_session) {
+//!     authenticated_client *client;
+//!     int code = new_client("123456789", cached_session, on_refresh, NULL, &client);
+//!     if (code) {
+//!       printf("auth error: %d\n", code);
+//!       return;
+//!     }
+//!
+//!     ele_result *e;
+//!     code = client_query_ele(client, &e);
+//!     if (code == 0) {
+//!       printf("total surplus: %f\n", e->total_surplus);
+//!       free_ele_result(e);
+//!     }
+//!
+//!     free_client(client);
+//! }
+//! ```
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 use std::{
+    any::Any,
+    cell::RefCell,
     ffi::{CStr, CString},
+    fmt::Display,
     os::raw::*,
+    panic::{self, AssertUnwindSafe},
 };
 
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::Error;
+use crate::req::{login::LoginHandler, url::Endpoints, Handler};
+
+/// Unhandled error
+const UNHANDLED: c_int = 101;
+/// Initialize login handler failed
+const INIT_HANDLER: c_int = 203;
+/// Get captcha image failed
+const CAPTCHA_IMAGE: c_int = 207;
+/// Null pointer or non UTF-8 string
+const INVALID_ARGUMENT: c_int = 208;
+/// Request rejected by the platform API
+const API: c_int = 209;
+/// App login token revoked
+const TOKEN_REVOKED: c_int = 210;
+/// Bad phone number or password
+const BAD_PASSWORD: c_int = 211;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<FfiError>> = const { RefCell::new(None) };
+}
+
+/// Error of C bind call, reported by the status code and kept as the last error
+#[derive(Debug)]
+struct FfiError {
+    code: c_int,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: c_int, message: impl Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// Null pointer of the argument
+    fn null(name: &str) -> Self {
+        Self::new(INVALID_ARGUMENT, format!("`{name}` is null"))
+    }
+}
+
+impl From<Error> for FfiError {
+    fn from(e: Error) -> Self {
+        let code = match e {
+            Error::AuthExpired => 201,
+            Error::NoBind => 202,
+            Error::BadPhoneNumber => 204,
+            Error::VerificationLimit => 205,
+            Error::BadVerificationCode => 206,
+            Error::Api { .. } => API,
+            Error::TokenRevoked => TOKEN_REVOKED,
+            Error::BadPassword => BAD_PASSWORD,
+            _ => UNHANDLED,
+        };
+        Self::new(code, e)
+    }
+}
+
+/// Run the C bind call
+/// -----------
+/// Catch the panic and keep the error of this thread, return the error code on failure.
+fn ffi_call<T>(f: impl FnOnce() -> Result<T, FfiError>) -> Result<T, c_int> {
+    LAST_ERROR.with(|e| e.borrow_mut().take());
+
+    let e = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(v)) => return Ok(v),
+        Ok(Err(e)) => e,
+        Err(payload) => FfiError::new(UNHANDLED, format!("Panic: {}", panic_message(&payload))),
+    };
+    let code = e.code;
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(e));
+
+    Err(code)
+}
+
+/// Run the C bind call returning status code
+fn ffi_status(f: impl FnOnce() -> Result<c_int, FfiError>) -> c_int {
+    ffi_call(f).unwrap_or_else(|code| code)
+}
+
+/// Run the C bind call returning pointer, nullptr on failure
+fn ffi_ptr<T>(f: impl FnOnce() -> Result<*mut T, FfiError>) -> *mut T {
+    ffi_call(f).unwrap_or(std::ptr::null_mut())
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown"
+    }
+}
+
+/// Error code of the last failed call -- C Bind
+/// -----------
+/// Kept per thread, cleared on the next call.
+///
+/// # Returns
+/// - `c_int`: error code, 0 if the last call succeeded
+///
+/// # Error codes
+/// - `101`: Unhandled error
+/// - `201`: Authentication expired
+/// - `202`: No bind info
+/// - `203`: Initialization handler error
+/// - `204`: Bad phone number
+/// - `205`: Limit of SMS verification code sent
+/// - `206`: Bad(Wrong) verification code
+/// - `207`: Get captcha image failed
+/// - `208`: Invalid argument, null pointer or non UTF-8 string
+/// - `209`: Request rejected by the platform API
+/// - `210`: App login token revoked, login again
+/// - `211`: Bad phone number or password
+#[no_mangle]
+pub extern "C" fn yxy_last_error_code() -> c_int {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(0, |e| e.code))
+}
+
+/// Error message of the last failed call -- C Bind
+/// -----------
+/// The caller is responsible for using `free_c_string` to deallocate the string.
+///
+/// Reading the last error does not clear it.
+///
+/// # Returns
+/// - `*mut c_char`: message c-string, UTF-8. Return nullptr if the last call succeeded.
+#[no_mangle]
+pub extern "C" fn yxy_last_error_message() -> *mut c_char {
+    LAST_ERROR.with(|e| match e.borrow().as_ref() {
+        // Interior nul is replaced, the message is never dropped
+        Some(e) => CString::new(e.message.replace('\0', " "))
+            .map_or(std::ptr::null_mut(), CString::into_raw),
+        None => std::ptr::null_mut(),
+    })
+}
+
 /// Authorization -- C Bind
 /// ----------
 /// # Inputs
@@ -122,16 +341,12 @@ use std::{
 /// - `*mut c_char`: session c-string, UTF-8. Return nullptr on error.
 #[no_mangle]
 pub extern "C" fn auth(uid: *const c_char) -> *mut c_char {
-    assert!(!uid.is_null());
-    let uid = unsafe { c_string_to_str(uid) };
+    ffi_ptr(|| {
+        let uid = unsafe { c_string_to_str(uid, "uid")? };
+        let (ses, _) = crate::auth(uid)?;
 
-    match crate::auth(uid) {
-        Ok((ses, _)) => CString::new(ses).unwrap().into_raw(),
-        Err(e) => {
-            eprintln!("{e}");
-            std::ptr::null_mut()
-        }
-    }
+        new_c_string(ses)
+    })
 }
 
 #[repr(C)]
@@ -150,7 +365,7 @@ pub struct ele_result {
 /// Copy `&str` to fixed-size `c_char` array
 pub fn copy_str_to_char_array<const L: usize>(s: &str) -> [c_char; L] {
     let mut c = [0 as c_char; L];
-    let len = s.as_bytes().len();
+    let len = s.len();
     if len > L - 1 {
         let slice = unsafe { std::slice::from_raw_parts(s.as_ptr() as *mut c_char, L - 1) };
         c[..L - 1].copy_from_slice(slice);
@@ -177,48 +392,598 @@ pub fn copy_str_to_char_array<const L: usize>(s: &str) -> [c_char; L] {
 /// # Errors (status codes)
 /// - `201`: Auth expired
 /// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
 /// - `101`: Other error
 #[no_mangle]
 pub extern "C" fn query_ele(session: *const c_char, result: *mut *mut ele_result) -> c_int {
-    assert!(!session.is_null());
-    assert!(!result.is_null());
-
-    let session = unsafe { c_string_to_str(session) };
-
-    match crate::query_ele(session) {
-        Ok(mut info) => unsafe {
-            let surplus = info.surplus_list.swap_remove(0);
-
-            (*result) = Box::into_raw(Box::new(ele_result {
-                total_surplus: info.soc,
-                total_amount: info.total_soc_amount,
-                surplus: surplus.surplus,
-                surplus_amount: surplus.amount,
-                subsidy: surplus.subsidy,
-                subsidy_amount: surplus.subsidy_amount,
-                display_room_name: CString::new(info.display_room_name).unwrap().into_raw(),
-                room_status: CString::new(surplus.room_status).unwrap().into_raw(),
-            }));
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let session = unsafe { c_string_to_str(session, "session")? };
 
-            0 // Return 0 for success
-        },
-        Err(e) => {
-            eprintln!("{e}");
-            match e {
-                crate::error::Error::AuthExpired => 201,
-                crate::error::Error::NoBind => 202,
-                _ => 101,
-            }
+        let info = crate::query_ele(session)?;
+        unsafe {
+            (*result) = new_ele_result(info)?;
         }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Convert electricity info to `ele_result` on heap
+fn new_ele_result(mut info: crate::req::app::ElectricityInfo) -> Result<*mut ele_result, FfiError> {
+    if info.surplus_list.is_empty() {
+        return Err(Error::EmptyResp.into());
     }
+    let surplus = info.surplus_list.swap_remove(0);
+
+    let display_room_name = new_c_string(info.display_room_name)?;
+    let room_status = match new_c_string(surplus.room_status) {
+        Ok(v) => v,
+        Err(e) => {
+            free_c_string(display_room_name);
+            return Err(e);
+        }
+    };
+
+    Ok(Box::into_raw(Box::new(ele_result {
+        total_surplus: info.soc,
+        total_amount: info.total_soc_amount,
+        surplus: surplus.surplus,
+        surplus_amount: surplus.amount,
+        subsidy: surplus.subsidy,
+        subsidy_amount: surplus.subsidy_amount,
+        display_room_name,
+        room_status,
+    })))
 }
 
 /// Free ele_result
 /// -----------
-/// Deallocate the struct to avoid memory leak.
+/// Deallocate the struct to avoid memory leak. Nullptr is ignored.
 #[no_mangle]
 pub extern "C" fn free_ele_result(p: *mut ele_result) {
-    assert!(!p.is_null());
+    if p.is_null() {
+        return;
+    }
+    unsafe {
+        let p = Box::from_raw(p);
+        free_c_string(p.display_room_name);
+        free_c_string(p.room_status);
+    }
+}
+
+/// Bind info
+/// -----------
+/// Room bound to the account, see `query_bind`.
+/// - `is_allow_change: c_int`: 0: not allowed, 1: allowed to change the bind
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct bind_info {
+    pub id: *mut c_char,
+    pub school_code: *mut c_char,
+    pub school_name: *mut c_char,
+    pub job_no: *mut c_char,
+    pub user_name: *mut c_char,
+    pub bind_type_str: *mut c_char,
+    pub area_id: *mut c_char,
+    pub area_name: *mut c_char,
+    pub building_code: *mut c_char,
+    pub building_name: *mut c_char,
+    pub floor_code: *mut c_char,
+    pub floor_name: *mut c_char,
+    pub room_code: *mut c_char,
+    pub room_name: *mut c_char,
+    pub create_time: *mut c_char,
+    pub is_allow_change: c_int,
+}
+
+impl From<crate::req::app::BindInfo> for bind_info {
+    fn from(v: crate::req::app::BindInfo) -> Self {
+        Self {
+            id: c_string_lossy(v.id),
+            school_code: c_string_lossy(v.school_code),
+            school_name: c_string_lossy(v.school_name),
+            job_no: c_string_lossy(v.job_no),
+            user_name: c_string_lossy(v.user_name),
+            bind_type_str: c_string_lossy(v.bind_type_str),
+            area_id: c_string_lossy(v.area_id),
+            area_name: c_string_lossy(v.area_name),
+            building_code: c_string_lossy(v.building_code),
+            building_name: c_string_lossy(v.building_name),
+            floor_code: c_string_lossy(v.floor_code),
+            floor_name: c_string_lossy(v.floor_name),
+            room_code: c_string_lossy(v.room_code),
+            room_name: c_string_lossy(v.room_name),
+            create_time: c_string_lossy(v.create_time),
+            is_allow_change: v.is_allow_change as c_int,
+        }
+    }
+}
+
+impl Drop for bind_info {
+    fn drop(&mut self) {
+        for p in [
+            self.id,
+            self.school_code,
+            self.school_name,
+            self.job_no,
+            self.user_name,
+            self.bind_type_str,
+            self.area_id,
+            self.area_name,
+            self.building_code,
+            self.building_name,
+            self.floor_code,
+            self.floor_name,
+            self.room_code,
+            self.room_name,
+            self.create_time,
+        ] {
+            free_c_string(p);
+        }
+    }
+}
+
+/// Bind query result
+/// -----------
+/// - `list: *mut bind_info`: array of `len` bind infos, the last one is queried by `query_ele`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct bind_result {
+    pub list: *mut bind_info,
+    pub len: usize,
+}
+
+impl Drop for bind_result {
+    fn drop(&mut self) {
+        unsafe { drop_c_array(self.list, self.len) }
+    }
+}
+
+/// Query bind infos -- C Bind
+/// -----------
+/// Query all rooms bound to the account.
+///
+/// After calling this function,
+/// the caller is responsible for using `free_bind_result` to deallocate the memory.
+///
+/// # Inputs
+/// - `session: *const c_char`: session c-string
+/// - `result: *mut *mut bind_result`: second-level pointer for return pointer of `bind_result` struct
+///
+/// # Returns
+/// - `c_int`: 0 on success, otherwise error code
+///
+/// # Errors (status codes)
+/// - `201`: Auth expired
+/// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn query_bind(session: *const c_char, result: *mut *mut bind_result) -> c_int {
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let session = unsafe { c_string_to_str(session, "session")? };
+
+        let binds = crate::req::Handler::new(session)?.query_binds()?;
+        unsafe {
+            (*result) = new_bind_result(binds);
+        }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Convert bind infos to `bind_result` on heap
+fn new_bind_result(binds: Vec<crate::req::app::BindInfo>) -> *mut bind_result {
+    let (list, len) = into_c_array(binds);
+    Box::into_raw(Box::new(bind_result { list, len }))
+}
+
+/// Free bind_result
+/// -----------
+/// Deallocate the struct with its bind infos to avoid memory leak. Nullptr is ignored.
+#[no_mangle]
+pub extern "C" fn free_bind_result(p: *mut bind_result) {
+    if p.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(p));
+    }
+}
+
+/// Electricity surplus of a meter
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct el_surplus {
+    pub surplus: c_float,
+    pub amount: c_float,
+    pub subsidy: c_float,
+    pub subsidy_amount: c_float,
+    pub total_surplus: c_float,
+    pub mdtype: *mut c_char,
+    pub mdname: *mut c_char,
+    pub room_status: *mut c_char,
+}
+
+impl From<crate::req::app::ElSurplus> for el_surplus {
+    fn from(v: crate::req::app::ElSurplus) -> Self {
+        Self {
+            surplus: v.surplus,
+            amount: v.amount,
+            subsidy: v.subsidy,
+            subsidy_amount: v.subsidy_amount,
+            total_surplus: v.total_surplus,
+            mdtype: c_string_lossy(v.mdtype),
+            mdname: c_string_lossy(v.mdname),
+            room_status: c_string_lossy(v.room_status),
+        }
+    }
+}
+
+impl Drop for el_surplus {
+    fn drop(&mut self) {
+        free_c_string(self.mdtype);
+        free_c_string(self.mdname);
+        free_c_string(self.room_status);
+    }
+}
+
+/// Top-up type of the room
+/// -----------
+/// - `cztype: *mut c_char`: type code to recharge by
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct el_top_up_type {
+    pub mdname: *mut c_char,
+    pub cztype: *mut c_char,
+}
+
+impl From<crate::req::app::ElTopUpType> for el_top_up_type {
+    fn from(v: crate::req::app::ElTopUpType) -> Self {
+        Self {
+            mdname: c_string_lossy(v.mdname),
+            cztype: c_string_lossy(v.cztype),
+        }
+    }
+}
+
+impl Drop for el_top_up_type {
+    fn drop(&mut self) {
+        free_c_string(self.mdname);
+        free_c_string(self.cztype);
+    }
+}
+
+/// Electricity info
+/// -----------
+/// Full electricity info of the room, unlike the flattened `ele_result`.
+/// - `surplus_list: *mut el_surplus`: array of `surplus_len` surplus
+/// - `top_up_type_list: *mut el_top_up_type`: array of `top_up_type_len` top-up types
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct ele_info {
+    pub school_code: *mut c_char,
+    pub area_id: *mut c_char,
+    pub building_code: *mut c_char,
+    pub floor_code: *mut c_char,
+    pub room_code: *mut c_char,
+    pub display_room_name: *mut c_char,
+    pub soc: c_float,
+    pub total_soc_amount: c_float,
+    pub is_allow_change: c_int,
+    pub show_type: c_int,
+    pub record_show: c_int,
+    pub style: c_int,
+    pub surplus_list: *mut el_surplus,
+    pub surplus_len: usize,
+    pub top_up_type_list: *mut el_top_up_type,
+    pub top_up_type_len: usize,
+}
+
+impl From<crate::req::app::ElectricityInfo> for ele_info {
+    fn from(v: crate::req::app::ElectricityInfo) -> Self {
+        let (surplus_list, surplus_len) = into_c_array(v.surplus_list);
+        let (top_up_type_list, top_up_type_len) = into_c_array(v.top_up_type_list);
+
+        Self {
+            school_code: c_string_lossy(v.school_code),
+            area_id: c_string_lossy(v.area_id),
+            building_code: c_string_lossy(v.building_code),
+            floor_code: c_string_lossy(v.floor_code),
+            room_code: c_string_lossy(v.room_code),
+            display_room_name: c_string_lossy(v.display_room_name),
+            soc: v.soc,
+            total_soc_amount: v.total_soc_amount,
+            is_allow_change: v.is_allow_change as c_int,
+            show_type: v.show_type as c_int,
+            record_show: v.record_show as c_int,
+            style: v.style as c_int,
+            surplus_list,
+            surplus_len,
+            top_up_type_list,
+            top_up_type_len,
+        }
+    }
+}
+
+impl Drop for ele_info {
+    fn drop(&mut self) {
+        for p in [
+            self.school_code,
+            self.area_id,
+            self.building_code,
+            self.floor_code,
+            self.room_code,
+            self.display_room_name,
+        ] {
+            free_c_string(p);
+        }
+        unsafe {
+            drop_c_array(self.surplus_list, self.surplus_len);
+            drop_c_array(self.top_up_type_list, self.top_up_type_len);
+        }
+    }
+}
+
+/// Query full electricity info -- C Bind
+/// -----------
+/// Like `query_ele`, with all surplus and top-up types of the room.
+///
+/// After calling this function,
+/// the caller is responsible for using `free_ele_info` to deallocate the memory.
+///
+/// # Inputs
+/// - `session: *const c_char`: session c-string
+/// - `result: *mut *mut ele_info`: second-level pointer for return pointer of `ele_info` struct
+///
+/// # Returns
+/// - `c_int`: 0 on success, otherwise error code
+///
+/// # Errors (status codes)
+/// - `201`: Auth expired
+/// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn query_ele_info(session: *const c_char, result: *mut *mut ele_info) -> c_int {
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let session = unsafe { c_string_to_str(session, "session")? };
+
+        let info = crate::query_ele(session)?;
+        unsafe {
+            (*result) = Box::into_raw(Box::new(ele_info::from(info)));
+        }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Free ele_info
+/// -----------
+/// Deallocate the struct with its arrays to avoid memory leak. Nullptr is ignored.
+#[no_mangle]
+pub extern "C" fn free_ele_info(p: *mut ele_info) {
+    if p.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(p));
+    }
+}
+
+/// Session refreshed callback
+/// -----------
+/// - `session: *const c_char`: new session c-string, only valid during the call
+/// - `user_data: *mut c_void`: pointer passed to `new_client`
+#[allow(non_camel_case_types)]
+pub type session_callback = Option<extern "C" fn(session: *const c_char, user_data: *mut c_void)>;
+
+/// Authenticated client, opaque to C
+///
+/// Reauthorize by the uid on expiry and retry the request once.
+#[allow(non_camel_case_types)]
+pub struct authenticated_client(crate::client::AuthenticatedClient);
+
+/// User data pointer moved into the callback
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+impl UserData {
+    fn ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// New authenticated client -- C Bind
+/// -----------
+/// The caller is responsible for using `free_client` to deallocate the client.
+///
+/// # Inputs
+/// - `uid: *const c_char`: uid c-string
+/// - `session: *const c_char`: cached session c-string.
+///   If `NULL`, authorize by the uid now.
+/// - `callback: session_callback`: called on the session refreshed, e.g. to persist it. Nullable.
+/// - `user_data: *mut c_void`: passed to the callback
+/// - `result: *mut *mut authenticated_client`: second-level pointer for return pointer of the client
+///
+/// # Returns
+/// - `c_int`: 0 on success, otherwise error code
+///
+/// # Errors
+/// - `208`: Invalid argument
+/// - `209`: Authorization rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn new_client(
+    uid: *const c_char,
+    session: *const c_char,
+    callback: session_callback,
+    user_data: *mut c_void,
+    result: *mut *mut authenticated_client,
+) -> c_int {
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let uid = unsafe { c_string_to_str(uid, "uid")? };
+
+        let endpoints = crate::req::url::Endpoints::default();
+        let client = if session.is_null() {
+            crate::client::AuthenticatedClient::with_endpoints(uid, endpoints)
+        } else {
+            let session = unsafe { c_string_to_str(session, "session")? };
+            crate::client::AuthenticatedClient::with_session(
+                crate::session::Session::from_id(uid, session),
+                endpoints,
+            )
+        };
+        let mut client = client?;
+
+        if let Some(f) = callback {
+            let user_data = UserData(user_data);
+            client = client.on_refresh(move |s| {
+                // Session id is never nul-contained, skip otherwise
+                if let Ok(session) = CString::new(s.id.as_str()) {
+                    f(session.as_ptr(), user_data.ptr());
+                }
+            });
+        }
+        unsafe {
+            (*result) = Box::into_raw(Box::new(authenticated_client(client)));
+        }
+
+        Ok(0) // Success
+    })
+}
+
+/// Query electricity by client -- C Bind
+/// -----------
+/// Like `query_ele`, reauthorize and retry once if the session expired.
+///
+/// # Inputs
+/// - `client: *mut authenticated_client`: client created by `new_client`
+/// - `result: *mut *mut ele_result`: second-level pointer for return pointer of `ele_result` struct
+///
+/// # Returns
+/// - `c_int`: 0 on success, otherwise error code
+///
+/// # Errors (status codes)
+/// - `201`: Auth expired after retry
+/// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn client_query_ele(
+    client: *mut authenticated_client,
+    result: *mut *mut ele_result,
+) -> c_int {
+    ffi_status(|| {
+        if client.is_null() {
+            return Err(FfiError::null("client"));
+        }
+        check_out_ptr(result, "result")?;
+
+        let info = unsafe { (*client).0.query_ele()? };
+        unsafe {
+            (*result) = new_ele_result(info)?;
+        }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Query bind infos by client -- C Bind
+/// -----------
+/// Like `query_bind`, reauthorize and retry once if the session expired.
+///
+/// # Errors (status codes)
+/// - `201`: Auth expired after retry
+/// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn client_query_bind(
+    client: *mut authenticated_client,
+    result: *mut *mut bind_result,
+) -> c_int {
+    ffi_status(|| {
+        if client.is_null() {
+            return Err(FfiError::null("client"));
+        }
+        check_out_ptr(result, "result")?;
+
+        let binds = unsafe { (*client).0.query_binds()? };
+        unsafe {
+            (*result) = new_bind_result(binds);
+        }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Query full electricity info by client -- C Bind
+/// -----------
+/// Like `query_ele_info`, reauthorize and retry once if the session expired.
+///
+/// # Errors (status codes)
+/// - `201`: Auth expired after retry
+/// - `202`: No bind info
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn client_query_ele_info(
+    client: *mut authenticated_client,
+    result: *mut *mut ele_info,
+) -> c_int {
+    ffi_status(|| {
+        if client.is_null() {
+            return Err(FfiError::null("client"));
+        }
+        check_out_ptr(result, "result")?;
+
+        let info = unsafe { (*client).0.query_ele()? };
+        unsafe {
+            (*result) = Box::into_raw(Box::new(ele_info::from(info)));
+        }
+
+        Ok(0) // Return 0 for success
+    })
+}
+
+/// Current session of client -- C Bind
+/// -----------
+/// The caller is responsible for using `free_c_string` to deallocate the string.
+///
+/// # Returns
+/// - `*mut c_char`: session c-string. Return nullptr on error.
+#[no_mangle]
+pub extern "C" fn client_session(client: *const authenticated_client) -> *mut c_char {
+    ffi_ptr(|| {
+        if client.is_null() {
+            return Err(FfiError::null("client"));
+        }
+        let session = unsafe { (*client).0.session() };
+
+        new_c_string(session.id.as_str())
+    })
+}
+
+/// Free authenticated_client
+/// -----------
+/// Deallocate the client to avoid memory leak. Nullptr is ignored.
+#[no_mangle]
+pub extern "C" fn free_client(p: *mut authenticated_client) {
+    if p.is_null() {
+        return;
+    }
     unsafe {
         drop(Box::from_raw(p));
     }
@@ -232,23 +997,21 @@ pub struct login_handle {
 }
 
 /// Initialize login handler
-fn init_handler(
-    handle: *const login_handle,
-) -> Result<crate::req::login::LoginHandler, crate::error::Error> {
-    assert!(!handle.is_null());
+/// -----------
+/// Return `203` error on invalid handle
+fn init_handler(handle: *const login_handle) -> Result<crate::req::login::LoginHandler, FfiError> {
+    let init_error = |e: FfiError| FfiError::new(INIT_HANDLER, e.message);
+    if handle.is_null() {
+        return Err(init_error(FfiError::null("handle")));
+    }
 
-    let phone_num = unsafe {
-        // let slice = std::slice::from_raw_parts((*handle).phone_num as *const u8, 11);
-        // std::str::from_utf8_unchecked(slice)
-        c_string_to_str((*handle).phone_num)
-    };
-    let device_id = unsafe {
-        // let slice = std::slice::from_raw_parts((*handle).device_id as *const u8, 37);
-        // std::str::from_utf8_unchecked(slice)
-        c_string_to_str((*handle).device_id)
-    };
+    let phone_num =
+        unsafe { c_string_to_str((*handle).phone_num, "handle.phone_num") }.map_err(init_error)?;
+    let device_id =
+        unsafe { c_string_to_str((*handle).device_id, "handle.device_id") }.map_err(init_error)?;
 
     crate::req::login::LoginHandler::init(phone_num, device_id)
+        .map_err(|e| FfiError::new(INIT_HANDLER, e))
 }
 
 /// Generate random device id -- C Bind
@@ -259,22 +1022,21 @@ fn init_handler(
 /// the string in the structure.
 ///
 /// # Inputs
-/// - `handle: *const login_handle`: Pointer of Login handle
+/// - `handle: *const login_handle`: Pointer of Login handle.
+///   Left untouched on error, see `yxy_last_error_code`.
 #[no_mangle]
 pub extern "C" fn gen_device_id(handler: *mut login_handle) {
-    assert!(!handler.is_null());
-
-    let device_id = crate::req::login::gen_device_id();
+    let _ = ffi_call(|| {
+        if handler.is_null() {
+            return Err(FfiError::null("handle"));
+        }
+        let device_id = new_c_string(crate::req::login::gen_device_id())?;
 
-    unsafe {
-        // let slice = std::slice::from_raw_parts(
-        //     device_id.as_ptr() as *mut c_char,
-        //     device_id.as_bytes().len(),
-        // );
-        // (*handler).device_id[..37].copy_from_slice(slice);
-        // (*handler).device_id[37] = '\0' as c_char;
-        (*handler).device_id = CString::new(device_id).unwrap().into_raw();
-    }
+        unsafe {
+            (*handler).device_id = device_id;
+        }
+        Ok(())
+    });
 }
 
 /// Security token result
@@ -301,47 +1063,54 @@ pub struct security_token_result {
 ///
 /// # Errors
 /// - `203`: Initialize login handler failed
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
 /// - `101: Other errors
 #[no_mangle]
 pub extern "C" fn get_security_token(
     handle: *const login_handle,
     result: *mut *mut security_token_result,
 ) -> c_int {
-    if let Ok(handler) = init_handler(handle) {
-        match handler.get_security_token() {
-            Ok(token) => unsafe {
-                (*result) = Box::into_raw(Box::new(security_token_result {
-                    level: token.level as c_int,
-                    token: CString::new(token.security_token).unwrap().into_raw(),
-                }));
-
-                0 // Return 0 for success
-            },
-            Err(_) => 101, // Return nullptr if error
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let handler = init_handler(handle)?;
+
+        let token = handler
+            .get_security_token()
+            .map_err(|e| FfiError::new(UNHANDLED, e))?;
+        unsafe {
+            (*result) = Box::into_raw(Box::new(security_token_result {
+                level: token.level as c_int,
+                token: new_c_string(token.security_token)?,
+            }));
         }
-    } else {
-        203 // Return error code if init_handler failed
-    }
+
+        Ok(0) // Return 0 for success
+    })
 }
 
 /// Free security_token_result
 /// -----------
-/// Deallocate the struct to avoid memory leak.
+/// Deallocate the struct to avoid memory leak. Nullptr is ignored.
 #[no_mangle]
 pub extern "C" fn free_security_token_result(p: *mut security_token_result) {
-    assert!(!p.is_null());
+    if p.is_null() {
+        return;
+    }
     unsafe {
-        drop(CString::from_raw((*p).token));
-        drop(Box::from_raw(p));
+        let p = Box::from_raw(p);
+        free_c_string(p.token);
     }
 }
 
 /// Free c_string
 /// -----------
-/// Deallocate c_string to avoid memory leak.
+/// Deallocate c_string to avoid memory leak. Nullptr is ignored.
 #[no_mangle]
 pub extern "C" fn free_c_string(c_string: *mut c_char) {
-    assert!(!c_string.is_null());
+    if c_string.is_null() {
+        return;
+    }
     unsafe {
         drop(CString::from_raw(c_string));
     }
@@ -362,41 +1131,33 @@ pub extern "C" fn free_c_string(c_string: *mut c_char) {
 /// - `result: *mut *mut c_char`: captcha image in base64
 ///
 /// # Errors
+/// - `203`: Initialize login handler failed
 /// - `207`: Get captcha image failed
+/// - `208`: Invalid argument
 /// - `101: Other errors
-///
-/// # Usage
-///
 #[no_mangle]
 pub extern "C" fn get_captcha_image(
     handle: *const login_handle,
     security_token: *const c_char,
     result: *mut *mut c_char,
 ) -> c_int {
-    assert!(!handle.is_null());
-    assert!(!security_token.is_null());
-
-    let handler = init_handler(handle).unwrap();
-    let security_token = unsafe { c_string_to_str(security_token) };
-    match handler.get_captcha_image(security_token) {
-        Ok(image) => {
-            unsafe {
-                (*result) = CString::new(image).unwrap().into_raw();
-            }
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let security_token = unsafe { c_string_to_str(security_token, "security_token")? };
+        let handler = init_handler(handle)?;
 
-            0 // Return 0 for success
+        let image = handler
+            .get_captcha_image(security_token)
+            .map_err(|e| match e {
+                Error::Api { .. } => FfiError::new(CAPTCHA_IMAGE, e),
+                _ => FfiError::new(UNHANDLED, e),
+            })?;
+        unsafe {
+            (*result) = new_c_string(image)?;
         }
 
-        Err(e) => {
-            eprintln!("{}", e);
-            match e {
-                crate::error::Error::Runtime(_) => {
-                    207 // Return 207 for get captcha image error
-                }
-                _ => 101, // Return 101 for other errors
-            }
-        }
-    }
+        Ok(0) // Return 0 for success
+    })
 }
 
 /// Send SMS verification code -- C Bind
@@ -405,13 +1166,15 @@ pub extern "C" fn get_captcha_image(
 /// - `handle: *const login_handle`: Pointer of Login handle
 /// - `security_token: *const c_char`: c-string of security token
 /// - `captcha: *const c_char`: c-string of captcha.
-/// If captcha input `NULL`, it means no captcha is required.
+///   If captcha input `NULL`, it means no captcha is required.
 /// # Returns
 /// - `c_int`: `0` on success, `1` on user is not exist(registered), otherwise error code
 /// # Errors
 /// - `203`: Initialize login handler failed
 /// - `204`: Bad phone number
 /// - `205`: Limit of SMS verification code sent
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
 /// - `101: Other errors
 #[no_mangle]
 pub extern "C" fn send_verification_code(
@@ -419,32 +1182,21 @@ pub extern "C" fn send_verification_code(
     security_token: *const c_char,
     captcha: *const c_char,
 ) -> c_int {
-    if let Ok(handler) = init_handler(handle) {
+    ffi_status(|| {
+        let security_token = unsafe { c_string_to_str(security_token, "security_token")? };
         let captcha = if captcha.is_null() {
             None
         } else {
-            Some(unsafe { c_string_to_str(captcha) })
+            Some(unsafe { c_string_to_str(captcha, "captcha")? })
         };
-        match handler.send_verification_code(unsafe { c_string_to_str(security_token) }, captcha) {
-            Ok(v) => {
-                if v {
-                    0 // Success
-                } else {
-                    1 // Return 1 if user is not exist
-                }
-            }
-            Err(e) => {
-                eprintln!("{e}");
-                match e {
-                    crate::error::Error::BadPhoneNumber => 204, // Bad phone number
-                    crate::error::Error::VerificationLimit => 205, // Too may requests, limited
-                    _ => 101,                                   // Return 101 on other error
-                }
-            }
+        let handler = init_handler(handle)?;
+
+        if handler.send_verification_code(security_token, captcha)? {
+            Ok(0) // Success
+        } else {
+            Ok(1) // Return 1 if user is not exist
         }
-    } else {
-        203 // Init handler error
-    }
+    })
 }
 
 /// Login result
@@ -471,6 +1223,8 @@ pub struct login_result {
 /// # Errors
 /// - `203`: Initialize login handler failed
 /// - `206`: Bad(Wrong) verification code
+/// - `208`: Invalid argument
+/// - `209`: Request rejected by the platform API
 /// - `101: Other errors
 #[no_mangle]
 pub extern "C" fn do_login(
@@ -478,52 +1232,393 @@ pub extern "C" fn do_login(
     code: *const c_char,
     result: *mut *mut login_result,
 ) -> c_int {
-    assert!(!code.is_null());
-    assert!(!result.is_null());
-
-    if let Ok(handler) = init_handler(handle) {
-        match handler.do_login(unsafe { c_string_to_str(code) }) {
-            Ok(v) => unsafe {
-                (*result) = Box::into_raw(Box::new(login_result {
-                    uid: CString::new(v.id).unwrap().into_raw(),
-                    token: CString::new(v.token).unwrap().into_raw(),
-                    device_id: CString::new(v.device_id).unwrap().into_raw(),
-                    bind_card_status: v.bind_card_status as c_int,
-                }));
-
-                0 // Success
-            },
-            Err(e) => {
-                eprintln!("{e}");
-                match e {
-                    crate::error::Error::BadVerificationCode => 206, // Wrong verification code
-                    _ => 101,
-                }
-            }
+    ffi_status(|| {
+        check_out_ptr(result, "result")?;
+        let code = unsafe { c_string_to_str(code, "code")? };
+        let handler = init_handler(handle)?;
+
+        let v = handler.do_login(code)?;
+        // Build in Rust first, nothing leaks on error
+        let uid = CString::new(v.id).map_err(|e| FfiError::new(UNHANDLED, e))?;
+        let token = CString::new(v.token).map_err(|e| FfiError::new(UNHANDLED, e))?;
+        let device_id = CString::new(v.device_id).map_err(|e| FfiError::new(UNHANDLED, e))?;
+        unsafe {
+            (*result) = Box::into_raw(Box::new(login_result {
+                uid: uid.into_raw(),
+                token: token.into_raw(),
+                device_id: device_id.into_raw(),
+                bind_card_status: v.bind_card_status as c_int,
+            }));
         }
-    } else {
-        203 // Initialize login handler failed
-    }
+
+        Ok(0) // Success
+    })
 }
 
 /// Free login_result
 /// ---------
-/// Deallocate the struct to avoid memory leak.
+/// Deallocate the struct to avoid memory leak. Nullptr is ignored.
 #[no_mangle]
 pub extern "C" fn free_login_result(p: *mut login_result) {
-    assert!(!p.is_null());
+    if p.is_null() {
+        return;
+    }
     unsafe {
-        drop(CString::from_raw((*p).uid));
-        drop(CString::from_raw((*p).token));
-        drop(CString::from_raw((*p).device_id));
-        drop(Box::from_raw(p));
+        let p = Box::from_raw(p);
+        free_c_string(p.uid);
+        free_c_string(p.token);
+        free_c_string(p.device_id);
+    }
+}
+
+/// Service base URLs of JSON call, each defaults to the production platform
+///
+/// Unlike [`Endpoints`] of the config, keys are in camelCase like the other arguments.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct JsonEndpoints {
+    auth: Option<String>,
+    application: Option<String>,
+    app: Option<String>,
+    server_chan: Option<String>,
+}
+
+impl From<JsonEndpoints> for Endpoints {
+    fn from(v: JsonEndpoints) -> Self {
+        let default = Endpoints::default();
+        Self {
+            auth: v.auth.unwrap_or(default.auth),
+            application: v.application.unwrap_or(default.application),
+            app: v.app.unwrap_or(default.app),
+            server_chan: v.server_chan.unwrap_or(default.server_chan),
+        }
+    }
+}
+
+fn json_endpoints<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Endpoints, D::Error> {
+    JsonEndpoints::deserialize(d).map(Endpoints::from)
+}
+
+/// JSON call arguments of the session
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SessionArgs {
+    session: String,
+    #[serde(default, deserialize_with = "json_endpoints")]
+    endpoints: Endpoints,
+}
+
+/// JSON call arguments of the uid
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UidArgs {
+    uid: String,
+    #[serde(default, deserialize_with = "json_endpoints")]
+    endpoints: Endpoints,
+}
+
+/// JSON call arguments of the login steps, like `login_handle`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct LoginArgs {
+    phone_num: String,
+    device_id: String,
+    security_token: Option<String>,
+    captcha: Option<String>,
+    code: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+    #[serde(default, deserialize_with = "json_endpoints")]
+    endpoints: Endpoints,
+}
+
+impl LoginArgs {
+    fn handler(&self) -> Result<LoginHandler, FfiError> {
+        let mut handler = LoginHandler::init_with_endpoints(
+            &self.phone_num,
+            &self.device_id,
+            self.endpoints.clone(),
+        )
+        .map_err(|e| FfiError::new(INIT_HANDLER, e))?;
+        handler.token = self.token.clone();
+
+        Ok(handler)
+    }
+
+    /// Take the required argument
+    fn required<'a>(v: &'a Option<String>, name: &str) -> Result<&'a str, FfiError> {
+        v.as_deref()
+            .ok_or_else(|| FfiError::new(INVALID_ARGUMENT, format!("`{name}` is required")))
+    }
+}
+
+/// Call by method name and JSON arguments -- C Bind
+/// -----------
+/// JSON variant of the C Binds, for dynamic-language bindings.
+/// Arguments and results are the serde JSON of the Rust types, in camelCase.
+///
+/// The caller is responsible for using `free_c_string` to deallocate the result.
+///
+/// # Inputs
+/// - `method: *const c_char`: method name c-string
+/// - `json_args: *const c_char`: JSON object c-string of arguments, UTF-8. `NULL` for no arguments.
+///   An optional `endpoints` object `{auth, application, app, serverChan}` replaces the service base URLs,
+///   each defaults to the production platform. Unknown keys of arguments are rejected.
+///
+/// # Methods
+/// - `auth`: `{uid}` -> `{session, user}`
+/// - `query_bind`: `{session}` -> list of bind info
+/// - `query_ele`: `{session}` -> electricity info
+/// - `gen_device_id`: no arguments -> device id
+/// - `get_security_token`: `{phoneNum, deviceId}` -> `{level, securityToken}`
+/// - `get_captcha_image`: `{phoneNum, deviceId, securityToken}` -> captcha image in base64
+/// - `send_verification_code`: `{phoneNum, deviceId, securityToken, captcha?}`
+///   -> `false` on user is not exist(registered)
+/// - `do_login`: `{phoneNum, deviceId, code}` -> login response
+/// - `do_login_by_password`: `{phoneNum, deviceId, password}` -> login response
+/// - `do_silent_login`: `{phoneNum, deviceId, token}` -> login response
+///
+/// # Returns
+/// - `*mut c_char`: JSON c-string, `{"code": 0, "data": ...}` on success,
+///   otherwise `{"code": <error code>, "message": "..."}`. Error codes are the same as the C Binds.
+///
+/// # Errors
+/// - `201`: Auth expired, of `query_bind` and `query_ele`
+/// - `202`: No bind info, of `query_bind` and `query_ele`
+/// - `203`: Initialize login handler failed, of the login methods
+/// - `204`: Bad phone number, of `send_verification_code`
+/// - `205`: Limit of SMS verification code sent, of `send_verification_code`
+/// - `206`: Bad(Wrong) verification code, of `do_login`
+/// - `207`: Get captcha image failed, of `get_captcha_image`
+/// - `208`: Invalid argument, unknown method or bad arguments
+/// - `209`: Request rejected by the platform API
+/// - `210`: App login token revoked, of `do_silent_login`
+/// - `211`: Bad phone number or password, of `do_login_by_password`
+/// - `101`: Other error
+#[no_mangle]
+pub extern "C" fn yxy_call(method: *const c_char, json_args: *const c_char) -> *mut c_char {
+    let result = ffi_call(|| {
+        let method = unsafe { c_string_to_str(method, "method")? };
+        let args = if json_args.is_null() {
+            "{}"
+        } else {
+            unsafe { c_string_to_str(json_args, "json_args")? }
+        };
+
+        call_json(method, args)
+    });
+
+    let resp = match result {
+        Ok(data) => json!({ "code": 0, "data": data }),
+        Err(code) => {
+            let message = LAST_ERROR.with(|e| e.borrow().as_ref().map(|e| e.message.clone()));
+            json!({ "code": code, "message": message })
+        }
+    };
+    c_string_lossy(resp.to_string())
+}
+
+/// Dispatch the JSON call
+fn call_json(method: &str, args: &str) -> Result<serde_json::Value, FfiError> {
+    match method {
+        "auth" => {
+            let args: UidArgs = parse_args(args)?;
+            let (session, user) = crate::auth_with_endpoints(&args.uid, &args.endpoints)?;
+            to_json(json!({ "session": session, "user": user }))
+        }
+        "query_bind" => {
+            let args: SessionArgs = parse_args(args)?;
+            to_json(Handler::with_endpoints(&args.session, args.endpoints)?.query_binds()?)
+        }
+        "query_ele" => {
+            let args: SessionArgs = parse_args(args)?;
+            to_json(crate::query_ele_with_endpoints(
+                &args.session,
+                args.endpoints,
+            )?)
+        }
+        "gen_device_id" => to_json(crate::req::login::gen_device_id()),
+        "get_security_token" => {
+            let args: LoginArgs = parse_args(args)?;
+            to_json(args.handler()?.get_security_token()?)
+        }
+        "get_captcha_image" => {
+            let args: LoginArgs = parse_args(args)?;
+            let security_token = LoginArgs::required(&args.security_token, "securityToken")?;
+            let image = args
+                .handler()?
+                .get_captcha_image(security_token)
+                .map_err(|e| match e {
+                    Error::Api { .. } => FfiError::new(CAPTCHA_IMAGE, e),
+                    _ => e.into(),
+                })?;
+            to_json(image)
+        }
+        "send_verification_code" => {
+            let args: LoginArgs = parse_args(args)?;
+            let security_token = LoginArgs::required(&args.security_token, "securityToken")?;
+            to_json(
+                args.handler()?
+                    .send_verification_code(security_token, args.captcha.as_deref())?,
+            )
+        }
+        "do_login" => {
+            let args: LoginArgs = parse_args(args)?;
+            let code = LoginArgs::required(&args.code, "code")?;
+            to_json(args.handler()?.do_login(code)?)
+        }
+        "do_login_by_password" => {
+            let args: LoginArgs = parse_args(args)?;
+            let password = LoginArgs::required(&args.password, "password")?;
+            to_json(args.handler()?.do_login_by_password(password)?)
+        }
+        "do_silent_login" => {
+            let args: LoginArgs = parse_args(args)?;
+            LoginArgs::required(&args.token, "token")?;
+            to_json(args.handler()?.do_silent_login()?)
+        }
+        _ => Err(FfiError::new(
+            INVALID_ARGUMENT,
+            format!("Unknown method: {method}"),
+        )),
     }
 }
 
+fn parse_args<T: DeserializeOwned>(args: &str) -> Result<T, FfiError> {
+    serde_json::from_str(args)
+        .map_err(|e| FfiError::new(INVALID_ARGUMENT, format!("Bad arguments: {e}")))
+}
+
+fn to_json<T: Serialize>(v: T) -> Result<serde_json::Value, FfiError> {
+    serde_json::to_value(v).map_err(|e| FfiError::new(UNHANDLED, e))
+}
+
 /// Convert c-string to &str
 /// -----------
-/// `unsafe`: unchecked
-unsafe fn c_string_to_str<'a>(c_str: *const c_char) -> &'a str {
-    let c_str = CStr::from_ptr(c_str);
-    std::str::from_utf8_unchecked(c_str.to_bytes())
+/// `unsafe`: the pointer must be nullptr or a nul-terminated string
+unsafe fn c_string_to_str<'a>(c_str: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if c_str.is_null() {
+        return Err(FfiError::null(name));
+    }
+    CStr::from_ptr(c_str)
+        .to_str()
+        .map_err(|e| FfiError::new(INVALID_ARGUMENT, format!("`{name}` is not UTF-8: {e}")))
+}
+
+/// Convert to c-string on heap, owned by the caller
+fn new_c_string<S: Into<Vec<u8>>>(s: S) -> Result<*mut c_char, FfiError> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|e| FfiError::new(UNHANDLED, e))
+}
+
+/// Check the second-level pointer for result
+fn check_out_ptr<T>(p: *mut *mut T, name: &str) -> Result<(), FfiError> {
+    if p.is_null() {
+        return Err(FfiError::null(name));
+    }
+    Ok(())
+}
+
+/// Convert to c-string on heap, dropping interior nul
+fn c_string_lossy(mut s: String) -> *mut c_char {
+    s.retain(|c| c != '\0');
+    CString::new(s).unwrap_or_default().into_raw()
+}
+
+/// Move the items to C array on heap, return the pointer and length
+fn into_c_array<T, U: From<T>>(v: Vec<T>) -> (*mut U, usize) {
+    let array: Box<[U]> = v.into_iter().map(U::from).collect();
+    let len = array.len();
+
+    (Box::into_raw(array) as *mut U, len)
+}
+
+/// Deallocate the C array of `into_c_array`
+/// -----------
+/// `unsafe`: the pointer and length must come from `into_c_array`
+unsafe fn drop_c_array<T>(p: *mut T, len: usize) {
+    if !p.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(p, len)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn last_error_message() -> Option<String> {
+        let p = yxy_last_error_message();
+        if p.is_null() {
+            return None;
+        }
+        let message = unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string();
+        free_c_string(p);
+        Some(message)
+    }
+
+    #[test]
+    fn last_error() {
+        assert!(auth(std::ptr::null()).is_null());
+        assert_eq!(yxy_last_error_code(), INVALID_ARGUMENT);
+        assert_eq!(last_error_message().unwrap(), "`uid` is null");
+
+        let mut result = std::ptr::null_mut();
+        let bad = b"\xff\0";
+        let code = query_ele(bad.as_ptr() as *const c_char, &mut result);
+        assert_eq!(code, INVALID_ARGUMENT);
+        assert!(last_error_message().unwrap().contains("not UTF-8"));
+        assert!(result.is_null());
+
+        let handle = login_handle {
+            phone_num: std::ptr::null_mut(),
+            device_id: std::ptr::null_mut(),
+        };
+        let code = get_security_token(&handle, &mut std::ptr::null_mut());
+        assert_eq!(code, INIT_HANDLER);
+        assert_eq!(yxy_last_error_code(), INIT_HANDLER);
+
+        // Panic is caught, then cleared by the next call
+        assert_eq!(ffi_status(|| panic!("boom")), UNHANDLED);
+        assert_eq!(last_error_message().unwrap(), "Panic: boom");
+        assert_eq!(ffi_status(|| Ok(0)), 0);
+        assert_eq!(yxy_last_error_code(), 0);
+        assert!(last_error_message().is_none());
+
+        // Nullptr is ignored
+        free_c_string(std::ptr::null_mut());
+        free_ele_result(std::ptr::null_mut());
+        free_login_result(std::ptr::null_mut());
+    }
+
+    fn c_str(p: *const c_char) -> &'static str {
+        unsafe { CStr::from_ptr(p) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn ele_info() {
+        let info: crate::req::app::ElectricityInfo = serde_json::from_value(serde_json::json!({
+            "schoolCode": "1", "areaId": "1", "buildingCode": "B01", "floorCode": "F03",
+            "roomCode": "R301", "displayRoomName": "B01\0-301", "soc": 42.5,
+            "totalSocAmount": 25.5, "isAllowChange": 1, "showType": 1, "recordShow": 1, "style": 1,
+            "surplusList": [
+                { "surplus": 40.0, "amount": 24.0, "subsidy": 2.5, "subsidyAmount": 1.5,
+                  "totalSurplus": 42.5, "mdtype": "1", "mdname": "照明", "roomStatus": "正常" },
+                { "surplus": 1.0, "amount": 0.6, "subsidy": 0.0, "subsidyAmount": 0.0,
+                  "totalSurplus": 1.0, "mdtype": "2", "mdname": "空调", "roomStatus": "正常" }
+            ],
+            "topUpTypeList": []
+        }))
+        .unwrap();
+
+        let p = Box::into_raw(Box::new(super::ele_info::from(info)));
+        let e = unsafe { &*p };
+        assert_eq!(c_str(e.display_room_name), "B01-301");
+        assert_eq!(e.surplus_len, 2);
+        let surplus = unsafe { std::slice::from_raw_parts(e.surplus_list, e.surplus_len) };
+        assert_eq!(c_str(surplus[1].mdname), "空调");
+        assert_eq!(surplus[1].surplus, 1.0);
+        assert_eq!(e.top_up_type_len, 0);
+        free_ele_info(p);
+    }
 }
@@ -0,0 +1,300 @@
+//! Electricity history
+//! ------------
+//! Append-only JSON Lines store of electricity snapshots,
+//! and consumption analytics derived from consecutive snapshots.
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::req::app::ElectricityInfo;
+
+/// Electricity snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Unix timestamp in seconds
+    pub time: i64,
+    /// Room key, see [`room_key`]
+    pub room: String,
+    pub room_name: String,
+    pub soc: f32,
+    pub total_soc_amount: f32,
+    pub surplus: Vec<SurplusRecord>,
+}
+
+/// Snapshot of [`crate::req::app::ElSurplus`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurplusRecord {
+    pub mdname: String,
+    pub surplus: f32,
+    pub amount: f32,
+    pub subsidy: f32,
+    pub subsidy_amount: f32,
+}
+
+impl Record {
+    pub fn new(info: &ElectricityInfo, time: i64) -> Self {
+        Self {
+            time,
+            room: room_key(
+                &info.area_id,
+                &info.building_code,
+                &info.floor_code,
+                &info.room_code,
+            ),
+            room_name: info.display_room_name.clone(),
+            soc: info.soc,
+            total_soc_amount: info.total_soc_amount,
+            surplus: info
+                .surplus_list
+                .iter()
+                .map(|s| SurplusRecord {
+                    mdname: s.mdname.clone(),
+                    surplus: s.surplus,
+                    amount: s.amount,
+                    subsidy: s.subsidy,
+                    subsidy_amount: s.subsidy_amount,
+                })
+                .collect(),
+        }
+    }
+
+    /// Local date of the snapshot, `None` if the time is out of range
+    pub fn date(&self) -> Option<NaiveDate> {
+        local_date(self.time)
+    }
+}
+
+/// Key of room, in `<area>-<building>-<floor>-<room>` format
+pub fn room_key(area_id: &str, building_code: &str, floor_code: &str, room_code: &str) -> String {
+    format!("{}-{}-{}-{}", area_id, building_code, floor_code, room_code)
+}
+
+/// History store file
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Append a snapshot
+    ///
+    /// Auto create file
+    pub fn append(&self, record: &Record) -> Result<(), Error> {
+        crate::utils::append_json_line(&self.path, record)
+    }
+
+    /// Load snapshots in time order, optionally of one room
+    ///
+    /// Return empty list if the file does not exist
+    pub fn load(&self, room: Option<&str>) -> Result<Vec<Record>, Error> {
+        let mut result: Vec<Record> = crate::utils::load_json_lines(&self.path)?;
+        if let Some(room) = room {
+            result.retain(|r| r.room == room);
+        }
+        result.sort_by_key(|r| r.time);
+
+        Ok(result)
+    }
+}
+
+/// Consumption between two consecutive snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    pub from: i64,
+    pub to: i64,
+    /// kW·h consumed, recharges are not counted
+    pub consumption: f32,
+}
+
+/// Derive usages from time ordered snapshots of one room
+pub fn usages(records: &[Record]) -> Vec<Usage> {
+    records
+        .windows(2)
+        .map(|w| Usage {
+            from: w[0].time,
+            to: w[1].time,
+            consumption: (w[0].soc - w[1].soc).max(0.0),
+        })
+        .collect()
+}
+
+/// Daily consumption, counted on the local date of the later snapshot
+pub fn daily_consumption(records: &[Record]) -> Vec<(NaiveDate, f32)> {
+    let mut result: Vec<(NaiveDate, f32)> = Vec::new();
+    for u in usages(records) {
+        let date = match local_date(u.to) {
+            Some(v) => v,
+            None => continue,
+        };
+        match result.last_mut() {
+            Some((d, v)) if *d == date => *v += u.consumption,
+            _ => result.push((date, u.consumption)),
+        }
+    }
+    result
+}
+
+/// Local time of Unix timestamp, `None` if out of range
+pub fn local_time(time: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(time, 0).earliest()
+}
+
+fn local_date(time: i64) -> Option<NaiveDate> {
+    local_time(time).map(|t| t.naive_local().date())
+}
+
+/// Average kW·h per day over the recorded period
+///
+/// Return `None` if the records cover no time
+pub fn average_per_day(records: &[Record]) -> Option<f32> {
+    let (first, last) = (records.first()?, records.last()?);
+    let days = last.time.saturating_sub(first.time) as f32 / 86400.0;
+    if days <= 0.0 {
+        return None;
+    }
+    let total: f32 = usages(records).iter().map(|u| u.consumption).sum();
+    Some(total / days)
+}
+
+/// Period of recent records used by [`forecast`], in days
+pub const FORECAST_DAYS: i64 = 14;
+
+/// Days remaining beyond are not forecast, e.g. by a tiny average consumption
+pub const MAX_FORECAST_DAYS: f32 = 3650.0;
+
+/// Estimation of running out of electricity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    /// Average kW·h per day of recent records
+    pub average: f32,
+    /// Days remaining until the surplus runs out
+    pub days: f32,
+    /// Unix timestamp of running out
+    pub empty_at: i64,
+}
+
+/// Forecast by the recent records of one room
+///
+/// Return `None` if there is no consumption recorded,
+/// or the days remaining exceed [`MAX_FORECAST_DAYS`]
+pub fn forecast(records: &[Record]) -> Option<Forecast> {
+    let last = records.last()?;
+    let since = last.time.saturating_sub(FORECAST_DAYS * 86400);
+    let recent: Vec<Record> = records
+        .iter()
+        .filter(|r| r.time >= since)
+        .cloned()
+        .collect();
+
+    let average = average_per_day(&recent)?;
+    if average <= 0.0 {
+        return None;
+    }
+    let days = last.soc.max(0.0) / average;
+    if days.is_nan() || days > MAX_FORECAST_DAYS {
+        return None;
+    }
+
+    Some(Forecast {
+        average,
+        days,
+        empty_at: last.time.checked_add((days * 86400.0) as i64)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(time: i64, soc: f32) -> Record {
+        Record {
+            time,
+            room: "1-B01-F03-R301".into(),
+            room_name: "301".into(),
+            soc,
+            total_soc_amount: soc * 0.5,
+            surplus: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn consumption() {
+        let day = 86400;
+        let records = vec![
+            record(0, 50.0),
+            record(day / 2, 45.0),
+            record(day, 40.0),
+            // recharged
+            record(day * 2, 80.0),
+            record(day * 3, 70.0),
+        ];
+
+        let usages = usages(&records);
+        assert_eq!(usages.len(), 4);
+        assert_eq!(usages[2].consumption, 0.0);
+
+        let total: f32 = daily_consumption(&records).iter().map(|(_, v)| v).sum();
+        assert_eq!(total, 20.0);
+        assert_eq!(average_per_day(&records), Some(20.0 / 3.0));
+        assert_eq!(average_per_day(&records[..1]), None);
+    }
+
+    #[test]
+    fn forecast_days() {
+        let day = 86400;
+        let records = vec![
+            // out of the recent period
+            record(-day * 30, 100.0),
+            record(0, 40.0),
+            record(day, 35.0),
+            record(day * 2, 30.0),
+        ];
+
+        let result = forecast(&records).unwrap();
+        assert_eq!(result.average, 5.0);
+        assert_eq!(result.days, 6.0);
+        assert_eq!(result.empty_at, day * 8);
+
+        // No consumption
+        assert_eq!(forecast(&[record(0, 40.0), record(day, 40.0)]), None);
+        // Tiny consumption, days remaining out of range
+        assert_eq!(
+            forecast(&[record(0, 1e30), record(day, 1e30 - 1e-30)]),
+            None
+        );
+        assert_eq!(forecast(&[record(0, 1e9), record(day, 1e9 - 1.0)]), None);
+
+        // Out of range timestamps are skipped, never panic
+        assert_eq!(record(i64::MAX, 1.0).date(), None);
+        let records = vec![record(0, 2.0), record(i64::MAX, 1.0)];
+        assert!(daily_consumption(&records).is_empty());
+    }
+
+    #[test]
+    fn store() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("yxy-history-{}", uuid::Uuid::new_v4()));
+        let history = History::new(&path);
+        assert!(history.load(None)?.is_empty());
+
+        let mut other = record(5, 10.0);
+        other.room = "other".into();
+        history.append(&record(10, 30.0))?;
+        history.append(&other)?;
+        history.append(&record(0, 40.0))?;
+
+        let records = history.load(Some("1-B01-F03-R301"))?;
+        assert_eq!(records, vec![record(0, 40.0), record(10, 30.0)]);
+        assert_eq!(history.load(None)?.len(), 3);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
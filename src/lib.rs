@@ -1,8 +1,12 @@
 //! YXY Spider Library
 
+pub mod client;
 pub mod error;
 pub mod ffi;
+pub mod history;
 pub mod req;
+pub mod session;
+pub mod store;
 pub mod utils;
 
 /// Authorization
@@ -12,31 +16,41 @@ pub mod utils;
 ///
 /// returns a tuple of (Session Token, User Info)
 pub fn auth(uid: &str) -> Result<(String, req::auth::UserInfo), error::Error> {
+    auth_with_endpoints(uid, &req::url::Endpoints::default())
+}
+
+/// Authorization with the given endpoints
+pub fn auth_with_endpoints(
+    uid: &str,
+    endpoints: &req::url::Endpoints,
+) -> Result<(String, req::auth::UserInfo), error::Error> {
     let client = req::init_default_client()?;
 
-    let oauth_code = req::auth::get_oauth_code(&client, uid)?;
+    let oauth_code = req::auth::get_oauth_code(&client, endpoints, uid)?;
 
-    let (ses, user) = req::auth::authorize(&client, &oauth_code)?;
+    let (ses, user) = req::auth::authorize(&client, endpoints, &oauth_code)?;
 
     Ok((ses, user))
 }
 
 /// Query electricity
 pub fn query_ele(session: &str) -> Result<req::app::ElectricityInfo, error::Error> {
+    query_ele_with_endpoints(session, req::url::Endpoints::default())
+}
+
+/// Query electricity with the given endpoints
+pub fn query_ele_with_endpoints(
+    session: &str,
+    endpoints: req::url::Endpoints,
+) -> Result<req::app::ElectricityInfo, error::Error> {
     // Init authorized handler
-    let handler = req::Handler::new(session)?;
+    let handler = req::Handler::with_endpoints(session, endpoints)?;
 
     // Query Bind Info
     let bind_info = handler.query_bind()?;
 
     // Query Electricity Info
-    let room_info = req::app::RoomInfo {
-        area_id: &bind_info.area_id,
-        building_code: &bind_info.building_code,
-        floor_code: &bind_info.floor_code,
-        room_code: &bind_info.room_code,
-    };
-    let electricity_info = handler.query_electricity(room_info)?;
+    let electricity_info = handler.query_electricity(bind_info.room_info())?;
 
     Ok(electricity_info)
 }
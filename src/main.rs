@@ -1,105 +1,534 @@
 use clap::Parser;
 use std::error::Error;
 
+use yxy::req::url::Endpoints;
 use yxy::*;
 
 mod arg;
 mod conf;
+mod recharge;
+mod watch;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let opts = arg::Options::parse();
 
     if let Some(v) = opts.command {
+        // Endpoints of the explicitly specified config
+        let endpoints = match &opts.config {
+            Some(c) => parse_conf(c)?.endpoints.unwrap_or_default(),
+            None => Endpoints::default(),
+        };
+
         match v {
-            arg::Commands::Query { query: q, arg: a } => match q {
+            arg::Commands::Query {
+                query: q,
+                arg: a,
+                room,
+                area,
+                building,
+                floor,
+                password,
+            } => match q {
                 arg::Query::Uid => {
-                    query_uid(&a, opts.verbose)?;
+                    query_uid(&a, password, &endpoints, opts.verbose)?;
                 }
                 arg::Query::Electricity => {
-                    let (result, _session) = query_ele(&a, None, opts.verbose)?;
-                    print_ele(&result);
+                    let (result, _session) =
+                        query_ele(&a, None, room.as_deref(), &endpoints, opts.verbose)?;
+                    print_ele(&result, None);
+                }
+                arg::Query::Bind => {
+                    query_binds(&a, &endpoints, opts.verbose)?;
+                }
+                arg::Query::Room => {
+                    query_room(&a, [area, building, floor, room], &endpoints, opts.verbose)?;
                 }
             },
+            arg::Commands::Watch { interval, times } => {
+                let conf = load_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+                let interval =
+                    match interval.or_else(|| conf.watch.as_ref().map(|w| w.interval.clone())) {
+                        Some(v) => watch::parse_interval(&v)?,
+                        None => {
+                            return Err(Box::new(yxy::error::Error::Runtime(
+                                "No watch interval specified".into(),
+                            )))
+                        }
+                    };
+                watch::watch(&conf, interval, times, opts.notify, opts.verbose);
+            }
+            arg::Commands::Recharge {
+                amount,
+                top_up_type,
+                account,
+                dry_run,
+            } => {
+                let conf = load_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+                let account = match conf.account(account.as_deref()) {
+                    Some(v) => v,
+                    None => {
+                        return Err(Box::new(yxy::error::Error::Runtime(
+                            "No such account configured".into(),
+                        )))
+                    }
+                };
+                recharge(
+                    &conf,
+                    &account,
+                    amount,
+                    top_up_type.as_deref(),
+                    dry_run,
+                    opts.verbose,
+                )?;
+            }
+            arg::Commands::Store { uid } => {
+                // The store may not have the uid yet
+                let conf = parse_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+                let store_file = match &conf.store_file {
+                    Some(v) => v,
+                    None => {
+                        return Err(Box::new(yxy::error::Error::Runtime(
+                            "No store file configured".into(),
+                        )))
+                    }
+                };
+                let store = store::Store::new(store_file);
+                let mut secrets = store.load(passphrase()?)?;
+                if uid.is_some() {
+                    secrets.uid = uid;
+                }
+                store.save(passphrase()?, &secrets)?;
+                println!(
+                    "Store saved. UID: {}, sessions: {}",
+                    if secrets.uid.is_some() {
+                        "set"
+                    } else {
+                        "unset"
+                    },
+                    secrets.sessions.len()
+                );
+            }
+            arg::Commands::Login { phone, step } => {
+                let conf = parse_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+                match step {
+                    None => login(&conf, phone.as_deref(), opts.verbose)?,
+                    Some(arg::LoginStep::Start { phone, captcha }) => {
+                        login_start(&conf, &phone, captcha.as_deref(), opts.verbose)?
+                    }
+                    Some(arg::LoginStep::Finish { code }) => login_finish(&conf, &code)?,
+                }
+            }
+            arg::Commands::History { room } => {
+                let conf = load_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+                match &conf.history_file {
+                    Some(v) => print_history(&history::History::new(v), room.as_deref())?,
+                    None => eprintln!("No history file config found"),
+                }
+            }
         }
     } else {
-        let conf_path = match &opts.config {
-            Some(c) => c,
-            None => "./conf.yaml",
-        };
+        let conf = load_conf(opts.config.as_deref().unwrap_or(DEFAULT_CONF))?;
+        let endpoints = conf.endpoints.clone().unwrap_or_default();
+        let accounts = conf.accounts();
 
-        let conf = match conf::Config::parse(&conf_path) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(Box::new(yxy::error::Error::Runtime(format!(
-                    "Read/Parse conf.yaml file error: {}",
-                    e
-                ))));
+        // Default query electricity of all accounts
+        let mut report = Vec::new();
+        let mut error = None;
+        for account in &accounts {
+            let result = query_account(&conf, account, &endpoints, opts.verbose).and_then(
+                |(result, forecast)| {
+                    if opts.notify {
+                        notify(account, &endpoints, &result, forecast.as_ref())?;
+                    }
+                    Ok((result, forecast))
+                },
+            );
+            if let Err(e) = &result {
+                eprintln!("Account {} error: {}", account.label(), e);
             }
-        };
+            report.push((account, result));
+        }
 
-        // Read the session cache
-        let session = match &conf.cookie_file {
-            None => None,
-            Some(cookie_file) => match std::fs::read_to_string(cookie_file) {
-                Ok(v) => {
-                    if opts.verbose {
-                        println!("Using cached session id: {}", v);
+        // Combined report
+        if !opts.notify {
+            for (account, result) in &report {
+                if let Ok((result, forecast)) = result {
+                    if accounts.len() > 1 {
+                        println!("Account: {}", account.label());
                     }
-                    Some(v)
-                }
-                Err(e) => {
-                    eprintln!("Session cache file reading error: {}", e);
-                    None
+                    print_ele(result, forecast.as_ref());
                 }
-            },
-        };
+            }
+        }
+        if accounts.len() > 1 {
+            print_summary(&report);
+        }
+
+        for (_, result) in report {
+            if let Err(e) = result {
+                error.get_or_insert(e);
+            }
+        }
+        if let Some(e) = error {
+            return Err(Box::new(e));
+        }
+    }
+
+    Ok(())
+}
+
+/// Query electricity of the account, cache the session and record the history
+fn query_account(
+    conf: &conf::Config,
+    account: &conf::Account,
+    endpoints: &Endpoints,
+    verbose: bool,
+) -> Result<(req::app::ElectricityInfo, Option<history::Forecast>), error::Error> {
+    // Read the session cache
+    let session = read_session_cache(account, verbose);
+
+    let (mut result, session) = query_ele(
+        &account.uid,
+        session,
+        account.room.as_deref(),
+        endpoints,
+        verbose,
+    )?;
+
+    // Automatic recharge
+    let mut recharge_error = None;
+    if let Some(rule) = &account.auto_recharge {
+        match recharge::auto_recharge(
+            rule,
+            account,
+            &session.id,
+            &result,
+            endpoints,
+            conf.allow_unverified_recharge,
+            verbose,
+        ) {
+            Ok(Some(v)) => result = v,
+            Ok(None) => {}
+            Err(e) => recharge_error = Some(e),
+        }
+    }
+
+    // Record the history & forecast
+    let forecast = match &conf.history_file {
+        Some(history_file) => record_history(history_file, &result, verbose),
+        None => None,
+    };
+
+    // Cache the session
+    cache_session(account, &session, verbose);
+
+    // Paid but unrecorded, the monthly limit is not enforced
+    if let Some(e) = recharge_error {
+        return Err(e);
+    }
+
+    Ok((result, forecast))
+}
+
+/// Push message of the account's electricity info
+fn notify(
+    account: &conf::Account,
+    endpoints: &Endpoints,
+    result: &req::app::ElectricityInfo,
+    forecast: Option<&history::Forecast>,
+) -> Result<(), error::Error> {
+    // Message push service
+    if let Some(sc) = &account.server_chan {
+        println!("Pushing message to ServerChan channel...");
+        if sc.is_warning(result.soc, forecast) {
+            yxy::req::notice::push_message(
+                endpoints,
+                &sc.key,
+                &format!("{}{}", &sc.warning_title, &result.soc),
+                &fmt_ele_md(result, forecast),
+            )?;
+        } else if sc.log_level == 0 {
+            yxy::req::notice::push_message(
+                endpoints,
+                &sc.key,
+                &format!("{}{}", &sc.title, &result.soc),
+                &fmt_ele_md(result, forecast),
+            )?;
+        } else {
+            println!("Nothing to do.");
+        }
+        println!("Success.")
+    } else {
+        eprintln!("No message push config found");
+    }
+
+    Ok(())
+}
+
+/// Print summary table of accounts
+#[allow(clippy::type_complexity)]
+fn print_summary(
+    report: &[(
+        &conf::Account,
+        Result<(req::app::ElectricityInfo, Option<history::Forecast>), error::Error>,
+    )],
+) {
+    println!(
+        "{:<16}  {:<24}  {:>14}  {:>20}",
+        "Account", "Room", "Surplus", "Remaining"
+    );
+    for (account, result) in report {
+        match result {
+            Ok((info, forecast)) => println!(
+                "{:<16}  {:<24}  {:>14}  {:>20}",
+                account.label(),
+                info.display_room_name,
+                format!("{} kW·h", info.soc),
+                forecast.as_ref().map_or("-".into(), fmt_forecast),
+            ),
+            Err(e) => println!("{:<16}  Error: {}", account.label(), e),
+        }
+    }
+}
+
+const DEFAULT_CONF: &str = "./conf.yaml";
+const DEFAULT_LOGIN_STATE: &str = "./login_state.json";
+
+/// Read & parse config file
+fn parse_conf(path: &str) -> Result<conf::Config, Box<dyn Error>> {
+    match conf::Config::parse(path) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(Box::new(yxy::error::Error::Runtime(format!(
+            "Read/Parse conf.yaml file error: {}",
+            e
+        )))),
+    }
+}
 
-        // Default query electricity
-        let (result, session) = query_ele(&conf.uid, session, opts.verbose)?;
+/// Read & parse config file, take the uid from the encrypted store or login profile if configured
+fn load_conf(path: &str) -> Result<conf::Config, Box<dyn Error>> {
+    let mut conf = parse_conf(path)?;
 
-        // Cache the session
-        if let Some(cookie_file) = &conf.cookie_file {
-            if let Err(e) = yxy::utils::file_write(&cookie_file, &session.unwrap()) {
-                eprintln!("Fail to cache the session id: {}", e);
-            } else if opts.verbose {
-                println!("Session cached.")
+    if conf.uid.is_none() {
+        // Fallback to the uid of login profile
+        conf.uid = match &conf.store_file {
+            Some(store_file) => {
+                let secrets = store::Store::new(store_file).load(passphrase()?)?;
+                secrets.uid.or_else(|| secrets.profile.map(|v| v.id))
             }
+            None => read_profile(&conf)?.map(|v| v.id),
+        };
+        if conf.uid.is_none() && conf.accounts.is_empty() {
+            return Err(Box::new(yxy::error::Error::Runtime(
+                "No uid found, save it by `yxy store --uid <UID>` or `yxy login <PHONE>`".into(),
+            )));
         }
+    }
 
-        // Notification
-        if opts.notify {
-            // Message push service
-            if let Some(sc) = conf.server_chan {
-                println!("Pushing message to ServerChan channel...");
-                if result.soc < sc.warning_threshold {
-                    yxy::req::notice::push_message(
-                        &sc.key,
-                        &format!("{}{}", &sc.warning_title, &result.soc),
-                        &fmt_ele_md(&result),
-                    )?;
-                } else if sc.log_level == 0 {
-                    yxy::req::notice::push_message(
-                        &sc.key,
-                        &format!("{}{}", &sc.title, &result.soc),
-                        &fmt_ele_md(&result),
-                    )?;
-                } else {
-                    println!("Nothing to do.");
-                }
-                println!("Success.")
-            } else {
-                eprintln!("No message push config found");
+    Ok(conf)
+}
+
+/// Read a secret line from stdin, prompted without echo on a terminal
+fn read_secret(prompt: &str) -> Result<String, error::Error> {
+    use std::io::IsTerminal;
+
+    if std::io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password(prompt)?);
+    }
+    // Piped
+    println!("{}", prompt);
+    let mut v = String::new();
+    std::io::stdin().read_line(&mut v)?;
+
+    Ok(v.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Passphrase of the encrypted store, from `YXY_PASSPHRASE` env or stdin
+fn passphrase() -> Result<&'static str, error::Error> {
+    static PASSPHRASE: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+    if let Some(v) = PASSPHRASE.get() {
+        return Ok(v);
+    }
+    let passphrase = match std::env::var("YXY_PASSPHRASE") {
+        Ok(v) => v,
+        Err(_) => read_secret("Please input the passphrase of store: ")?,
+    };
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+/// Read the saved app login profile
+fn read_profile(conf: &conf::Config) -> Result<Option<req::login::LoginResponse>, error::Error> {
+    match (&conf.store_file, &conf.profile_file) {
+        (Some(store_file), _) => Ok(store::Store::new(store_file).load(passphrase()?)?.profile),
+        (None, Some(profile_file)) => req::login::LoginResponse::load(profile_file),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Save the app login profile into the store or profile file
+fn save_profile(
+    conf: &conf::Config,
+    profile: &req::login::LoginResponse,
+) -> Result<(), error::Error> {
+    match (&conf.store_file, &conf.profile_file) {
+        (Some(store_file), _) => {
+            let p = passphrase()?;
+            let store = store::Store::new(store_file);
+            let mut secrets = store.load(p)?;
+            secrets.profile = Some(profile.clone());
+            store.save(p, &secrets)
+        }
+        (None, Some(profile_file)) => profile.save(profile_file),
+        (None, None) => Err(error::Error::Runtime(
+            "No store file or profile file configured".into(),
+        )),
+    }
+}
+
+/// Read the session cache of account
+fn read_session_cache(account: &conf::Account, verbose: bool) -> Option<session::Session> {
+    let result = match (&account.store_file, &account.cookie_file) {
+        (Some(store_file), _) => passphrase()
+            .and_then(|p| store::Store::new(store_file).load(p))
+            .map(|mut v| v.sessions.remove(&account.uid)),
+        (None, Some(cookie_file)) => session::Session::load(cookie_file, &account.uid),
+        (None, None) => return None,
+    };
+    match result {
+        Ok(v) => {
+            if let (true, Some(v)) = (verbose, &v) {
+                println!("Using cached session id: {}", v.id);
             }
-        } else {
-            print_ele(&result);
+            v
+        }
+        Err(e) => {
+            eprintln!("Session cache file reading error: {}", e);
+            None
+        }
+    }
+}
+
+/// Cache the session if configured
+fn cache_session(account: &conf::Account, session: &session::Session, verbose: bool) {
+    let result = match (&account.store_file, &account.cookie_file) {
+        (Some(store_file), _) => passphrase().and_then(|p| {
+            let store = store::Store::new(store_file);
+            let mut secrets = store.load(p)?;
+            secrets
+                .sessions
+                .insert(account.uid.clone(), session.clone());
+            store.save(p, &secrets)
+        }),
+        (None, Some(cookie_file)) => session.save(cookie_file),
+        (None, None) => return,
+    };
+    if let Err(e) = result {
+        eprintln!("Fail to cache the session: {}", e);
+    } else if verbose {
+        println!("Session cached.")
+    }
+}
+
+/// Append electricity info to history, and forecast by the room's records
+fn record_history(
+    history_file: &str,
+    info: &req::app::ElectricityInfo,
+    verbose: bool,
+) -> Option<history::Forecast> {
+    let history = history::History::new(history_file);
+    let record = history::Record::new(info, chrono::Local::now().timestamp());
+    if let Err(e) = history.append(&record) {
+        eprintln!("Fail to record the history: {}", e);
+    } else if verbose {
+        println!("History recorded.")
+    }
+
+    match history.load(Some(&record.room)) {
+        Ok(v) => history::forecast(&v),
+        Err(e) => {
+            eprintln!("Fail to load the history: {}", e);
+            None
+        }
+    }
+}
+
+/// Recharge procedure
+fn recharge(
+    conf: &conf::Config,
+    account: &conf::Account,
+    amount: f32,
+    top_up_type: Option<&str>,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<(), error::Error> {
+    let endpoints = conf.endpoints.clone().unwrap_or_default();
+    let session = read_session_cache(account, verbose);
+    let (info, session) = query_ele(
+        &account.uid,
+        session,
+        account.room.as_deref(),
+        &endpoints,
+        verbose,
+    )?;
+    cache_session(account, &session, verbose);
+
+    let top_up_type = match top_up_type {
+        Some(t) => info
+            .top_up_type_list
+            .iter()
+            .find(|v| v.cztype == t || v.mdname == t),
+        None => info.top_up_type_list.first(),
+    };
+    let top_up_type = match top_up_type {
+        Some(v) => v,
+        None => {
+            return Err(error::Error::Runtime(format!(
+                "No such top-up type, available: {}",
+                info.top_up_type_list
+                    .iter()
+                    .map(|v| format!("{} ({})", v.cztype, v.mdname))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )))
         }
+    };
+    let order = req::app::RechargeInfo::new(&info, top_up_type, amount)?;
+
+    println!(
+        "Recharge ￥{} of {} to room {}, surplus: {} kW·h",
+        order.amount, top_up_type.mdname, info.display_room_name, info.soc
+    );
+    if dry_run {
+        println!(
+            "Dry run, nothing paid. The order to submit:\nPOST {}\n{}",
+            endpoints.application_url(req::url::application::RECHARGE),
+            serde_json::to_string_pretty(&order)?
+        );
+        return Ok(());
     }
+    recharge::check_opt_in(conf.allow_unverified_recharge)?;
+
+    let handler = session.handler(endpoints)?;
+    let result = handler.recharge(&order)?;
+    println!(
+        "Recharged ￥{}, order no: {}",
+        result.amount, result.order_no
+    );
 
     Ok(())
 }
 
+/// fmt forecast, like `6.0 days (2022-10-08)`
+fn fmt_forecast(forecast: &history::Forecast) -> String {
+    match history::local_time(forecast.empty_at) {
+        Some(t) => format!("{:.1} days ({})", forecast.days, t.format("%Y-%m-%d")),
+        None => format!("{:.1} days", forecast.days),
+    }
+}
+
 /// fmt & print electricity info
-fn print_ele(info: &yxy::req::app::ElectricityInfo) {
+fn print_ele(info: &yxy::req::app::ElectricityInfo, forecast: Option<&history::Forecast>) {
     let surplus = &info.surplus_list[0];
     println!(
         "
@@ -123,12 +552,22 @@ Subsidy : {} kW·h | ￥{}
         surplus.subsidy,
         surplus.subsidy_amount,
     );
+    if let Some(f) = forecast {
+        println!(
+            "Average: {:.2} kW·h/day\nRemaining: {}\n",
+            f.average,
+            fmt_forecast(f)
+        );
+    }
 }
 
 /// fmt electricity info in markdown style
-pub fn fmt_ele_md(info: &yxy::req::app::ElectricityInfo) -> String {
+pub fn fmt_ele_md(
+    info: &yxy::req::app::ElectricityInfo,
+    forecast: Option<&history::Forecast>,
+) -> String {
     let surplus = &info.surplus_list[0];
-    format!(
+    let mut result = format!(
         "\
 # Electricity Info
 -----------------
@@ -149,55 +588,107 @@ pub fn fmt_ele_md(info: &yxy::req::app::ElectricityInfo) -> String {
         surplus.amount,
         surplus.subsidy,
         surplus.subsidy_amount,
-    )
+    );
+    if let Some(f) = forecast {
+        result.push_str(&format!(
+            "
+- Average: **{:.2}** kW·h/day
+- Remaining: **{}**
+",
+            f.average,
+            fmt_forecast(f)
+        ));
+    }
+
+    result
 }
 
-/// Query UID procedure
-fn query_uid(phone_num: &str, verbose: bool) -> Result<(), yxy::error::Error> {
-    let handler = yxy::req::login::LoginHandler::new(phone_num.to_string())?;
+/// fmt & print electricity history of rooms
+fn print_history(history: &history::History, room: Option<&str>) -> Result<(), error::Error> {
+    let records = history.load(room)?;
+    if records.is_empty() {
+        println!("No history recorded.");
+        return Ok(());
+    }
 
-    println!("Querying security token...");
-    let security_token = handler.get_security_token()?;
-    if verbose {
-        println!("Success: {:?}", security_token);
+    // Distinct rooms in order of appearance
+    let mut rooms: Vec<&str> = Vec::new();
+    for r in &records {
+        if !rooms.contains(&r.room.as_str()) {
+            rooms.push(&r.room);
+        }
     }
 
-    let mut captcha = String::new();
-    if security_token.level != 0 {
-        // image captcha required
-        println!("Image captcha required.");
-        let result = handler.get_captcha_image(&security_token.security_token)?;
+    for room in rooms {
+        let records: Vec<history::Record> =
+            records.iter().filter(|r| r.room == room).cloned().collect();
+        let last = records.last().unwrap();
+        println!(
+            "
+Electricity History:
+-----------------
+Room: {} ({})
+Records: {}
+Average: {}
+",
+            last.room_name,
+            room,
+            records.len(),
+            match history::average_per_day(&records) {
+                Some(v) => format!("{:.2} kW·h/day", v),
+                None => "-".into(),
+            },
+        );
 
-        println!("Captcha: {}", result);
+        println!("Daily consumption:");
+        for (date, consumption) in history::daily_consumption(&records) {
+            println!("{}    {:>8.2} kW·h", date, consumption);
+        }
 
-        println!("Please input the captcha: ");
-        std::io::stdin().read_line(&mut captcha)?;
+        println!("\n{:<19}  {:>14}  {:>14}", "Time", "Surplus", "Consumption");
+        let usages = history::usages(&records);
+        for (i, r) in records.iter().enumerate() {
+            let time = match history::local_time(r.time) {
+                Some(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => r.time.to_string(),
+            };
+            let consumption = match i {
+                0 => "-".to_string(),
+                _ => format!("{:.2} kW·h", usages[i - 1].consumption),
+            };
+            println!(
+                "{:<19}  {:>14}  {:>14}",
+                time,
+                format!("{:.2} kW·h", r.soc),
+                consumption
+            );
+        }
     }
 
-    println!("Sending verification code...");
-    let user_exists = handler.send_verification_code(
-        &security_token.security_token,
-        if security_token.level == 0 {
-            None
-        } else {
-            Some(&captcha)
-        },
-    )?;
+    Ok(())
+}
 
-    if user_exists == false {
-        eprintln!("Current user is not registered");
-    }
+/// Query UID procedure
+fn query_uid(
+    phone_num: &str,
+    password: bool,
+    endpoints: &Endpoints,
+    verbose: bool,
+) -> Result<(), yxy::error::Error> {
+    let handler = yxy::req::login::LoginHandler::init_with_endpoints(
+        phone_num,
+        &yxy::req::login::gen_device_id(),
+        endpoints.clone(),
+    )?;
+    let result = if password {
+        let pass = read_secret("Please input the password: ")?;
 
-    // Get code from stdin
-    let mut code = String::new();
-    println!("Send SMS successfully, please enter the verification code:");
-    std::io::stdin().read_line(&mut code)?;
+        println!("Login...");
+        handler.do_login_by_password(&pass)?
+    } else {
+        sms_login(&handler, verbose)?
+    };
 
-    println!("Login...");
-    let result = handler.do_login(&code)?;
-    if verbose {
-        println!("Login response: {:?}", result);
-    }
     println!("Login successfully. Here is your uid & other information:");
 
     // stdout infos
@@ -226,101 +717,416 @@ fn query_uid(phone_num: &str, verbose: bool) -> Result<(), yxy::error::Error> {
     Ok(())
 }
 
-/// Procedure of query electricity
-fn query_ele(
-    uid: &str,
-    mut session: Option<String>,
+/// Login by SMS verification code, read the code from stdin
+fn sms_login(
+    handler: &yxy::req::login::LoginHandler,
     verbose: bool,
-) -> Result<(req::app::ElectricityInfo, Option<String>), error::Error> {
-    let mut tried = false;
-    loop {
-        if session.is_none() {
-            let (ses, _) = app_auth(uid, verbose)?;
-            session.replace(ses);
+) -> Result<yxy::req::login::LoginResponse, yxy::error::Error> {
+    println!("Sending verification code...");
+    let user_exists = handler.request_verification_code()?;
+    if !user_exists {
+        eprintln!("Current user is not registered");
+    }
+
+    // Get code from stdin
+    let mut code = String::new();
+    println!("Send SMS successfully, please enter the verification code:");
+    std::io::stdin().read_line(&mut code)?;
+
+    println!("Login...");
+    let result = handler.do_login(&code)?;
+    if verbose {
+        println!("Login response: {:?}", result);
+    }
+
+    Ok(result)
+}
+
+/// External captcha solver of config
+fn captcha_solver(conf: &conf::Config) -> Option<req::captcha::CommandSolver> {
+    match conf.captcha_command.as_deref() {
+        Some([program, args @ ..]) => Some(req::captcha::CommandSolver::new(
+            program.as_str(),
+            args.to_vec(),
+        )),
+        _ => None,
+    }
+}
+
+/// Login procedure, save the profile by config
+///
+/// Try silent login by the saved profile of the phone first
+fn login(conf: &conf::Config, phone_num: Option<&str>, verbose: bool) -> Result<(), error::Error> {
+    check_profile_conf(conf)?;
+    let endpoints = conf.endpoints.clone().unwrap_or_default();
+
+    let saved = read_profile(conf)?;
+    let phone_num = match (phone_num, &saved) {
+        (Some(v), _) => v.to_string(),
+        (None, Some(v)) => v.mobile_phone.clone(),
+        (None, None) => {
+            return Err(error::Error::Runtime(
+                "No phone number given nor profile saved".into(),
+            ))
         }
-        match app_query_ele(session.as_ref().unwrap(), verbose) {
-            Err(e) => {
-                // Handle errors
-                match e {
-                    error::Error::AuthExpired => {
-                        if tried {
-                            return Err(error::Error::Auth(
-                                "Maximum auth retry number reached.".into(),
-                            ));
-                        }
-                        session.take();
-                        if verbose {
-                            eprintln!("Auth may expired, trying to reauthorize.")
-                        }
-                    }
-                    _ => return Err(e),
+    };
+
+    let handler = match saved {
+        Some(v) if v.mobile_phone == phone_num => {
+            // Keep the device id of the same phone
+            let handler = req::login::LoginHandler::from_profile(&v, endpoints)?;
+            match handler.do_silent_login() {
+                Ok(profile) => {
+                    save_profile(conf, &profile)?;
+                    println!("Login silently, profile saved. UID: {}", profile.id);
+                    return Ok(());
+                }
+                Err(error::Error::TokenRevoked) => {
+                    eprintln!("App login token revoked, login by SMS verification code")
                 }
-                tried = true;
+                Err(e) => return Err(e),
             }
-            Ok(v) => {
-                return Ok((v, session));
+            handler
+        }
+        _ => req::login::LoginHandler::init_with_endpoints(
+            &phone_num,
+            &req::login::gen_device_id(),
+            endpoints,
+        )?,
+    };
+    if verbose {
+        println!("Login by device id: {}", handler.device_id);
+    }
+    let handler = match captcha_solver(conf) {
+        Some(v) => handler.with_captcha_solver(v),
+        None => handler,
+    };
+    let profile = sms_login(&handler, verbose)?;
+
+    save_profile(conf, &profile)?;
+    println!("Login successfully, profile saved. UID: {}", profile.id);
+
+    Ok(())
+}
+
+/// First phase of login, send the verification code and save the pending login
+///
+/// Stop at the image captcha if required and no solver configured,
+/// continue by `captcha` with the saved security token
+fn login_start(
+    conf: &conf::Config,
+    phone_num: &str,
+    captcha: Option<&str>,
+    verbose: bool,
+) -> Result<(), error::Error> {
+    check_profile_conf(conf)?;
+    let endpoints = conf.endpoints.clone().unwrap_or_default();
+    let state_file = conf
+        .login_state_file
+        .as_deref()
+        .unwrap_or(DEFAULT_LOGIN_STATE);
+
+    let state = match (captcha, req::login::LoginState::load(state_file)?) {
+        (Some(_), Some(v)) if v.phone_num == phone_num => v,
+        _ => {
+            // Keep the device id of the same phone
+            let device_id = match read_profile(conf)? {
+                Some(v) if v.mobile_phone == phone_num => v.device_id,
+                _ => req::login::gen_device_id(),
+            };
+            let handler = req::login::LoginHandler::init_with_endpoints(
+                phone_num,
+                &device_id,
+                endpoints.clone(),
+            )?;
+            println!("Querying security token...");
+            let security_token = handler.get_security_token()?;
+            if verbose {
+                println!("Success: {:?}", security_token);
             }
+            handler.login_state(&security_token)
         }
+    };
+    state.save(state_file)?;
+    let mut handler = req::login::LoginHandler::from_state(&state, endpoints)?;
+    let solver = captcha_solver(conf);
+    let solvable = solver.is_some();
+    if let Some(v) = solver {
+        handler = handler.with_captcha_solver(v);
+    }
+
+    let captcha = match (state.level, captcha) {
+        (0, _) => None,
+        (_, Some(v)) => Some(v.to_string()),
+        (_, None) if solvable => Some(handler.solve_captcha(&state.security_token)?),
+        (_, None) => {
+            let image = handler.get_captcha(&state.security_token)?;
+            println!("Image captcha required.");
+            println!("Captcha: {}", image.save_temp()?.display());
+            println!(
+                "Continue by `yxy login start {} --captcha <CAPTCHA>`",
+                phone_num
+            );
+            return Ok(());
+        }
+    };
+
+    println!("Sending verification code...");
+    let user_exists = handler.send_verification_code(&state.security_token, captcha.as_deref())?;
+    if !user_exists {
+        eprintln!("Current user is not registered");
+    }
+    println!("Send SMS successfully, finish by `yxy login finish <CODE>`");
+
+    Ok(())
+}
+
+/// Second phase of login, login by the verification code and save the profile
+fn login_finish(conf: &conf::Config, code: &str) -> Result<(), error::Error> {
+    check_profile_conf(conf)?;
+    let state_file = conf
+        .login_state_file
+        .as_deref()
+        .unwrap_or(DEFAULT_LOGIN_STATE);
+    let state = match req::login::LoginState::load(state_file)? {
+        Some(v) => v,
+        None => {
+            return Err(error::Error::Runtime(
+                "No pending login, start by `yxy login start <PHONE>`".into(),
+            ))
+        }
+    };
+
+    let handler =
+        req::login::LoginHandler::from_state(&state, conf.endpoints.clone().unwrap_or_default())?;
+    println!("Login...");
+    let profile = handler.do_login(code)?;
+
+    save_profile(conf, &profile)?;
+    std::fs::remove_file(state_file)?;
+    println!("Login successfully, profile saved. UID: {}", profile.id);
+
+    Ok(())
+}
+
+/// The profile must be saved somewhere
+fn check_profile_conf(conf: &conf::Config) -> Result<(), error::Error> {
+    if conf.store_file.is_none() && conf.profile_file.is_none() {
+        return Err(error::Error::Runtime(
+            "No store file or profile file configured".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Query bound rooms procedure
+fn query_binds(uid: &str, endpoints: &Endpoints, verbose: bool) -> Result<(), error::Error> {
+    let handler = app_auth(uid, endpoints, verbose)?.handler(endpoints.clone())?;
+    let binds = handler.query_binds()?;
+    if verbose {
+        println!("Bind info: {:?}", binds);
+    }
+
+    println!(
+        "{:<24}  {:<16}  {:<16}  {:<12}  {:<12}",
+        "Room", "Area", "Building", "Floor", "Room name"
+    );
+    for bind in &binds {
+        println!(
+            "{:<24}  {:<16}  {:<16}  {:<12}  {:<12}",
+            bind_room_key(bind),
+            bind.area_name,
+            bind.building_name,
+            bind.floor_name,
+            bind.room_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Query room procedure
+///
+/// Browse area -> building -> floor -> room, query electricity if the room is specified
+fn query_room(
+    uid: &str,
+    location: [Option<String>; 4],
+    endpoints: &Endpoints,
+    verbose: bool,
+) -> Result<(), error::Error> {
+    // Each level requires its parent
+    const LEVELS: [&str; 4] = ["--area", "--building", "--floor", "--room"];
+    for i in 1..LEVELS.len() {
+        if location[i].is_some() && location[i - 1].is_none() {
+            return Err(error::Error::Runtime(format!(
+                "{} requires {}",
+                LEVELS[i],
+                LEVELS[i - 1]
+            )));
+        }
+    }
+
+    let handler = app_auth(uid, endpoints, verbose)?.handler(endpoints.clone())?;
+
+    let print_list = |title: &str, list: Vec<(String, String)>| {
+        println!("{:<16}  Name", title);
+        for (code, name) in list {
+            println!("{:<16}  {}", code, name);
+        }
+    };
+    match location {
+        [None, ..] => print_list(
+            "Area",
+            handler
+                .query_areas()?
+                .into_iter()
+                .map(|v| (v.id, v.area_name))
+                .collect(),
+        ),
+        [Some(area), None, ..] => print_list(
+            "Building",
+            handler
+                .query_buildings(&area)?
+                .into_iter()
+                .map(|v| (v.building_code, v.building_name))
+                .collect(),
+        ),
+        [Some(area), Some(building), None, _] => print_list(
+            "Floor",
+            handler
+                .query_floors(&area, &building)?
+                .into_iter()
+                .map(|v| (v.floor_code, v.floor_name))
+                .collect(),
+        ),
+        [Some(area), Some(building), Some(floor), None] => print_list(
+            "Room",
+            handler
+                .query_rooms(&area, &building, &floor)?
+                .into_iter()
+                .map(|v| (v.room_code, v.room_name))
+                .collect(),
+        ),
+        [Some(area), Some(building), Some(floor), Some(room)] => {
+            let result = handler.query_electricity(req::app::RoomInfo {
+                area_id: &area,
+                building_code: &building,
+                floor_code: &floor,
+                room_code: &room,
+            })?;
+            print_ele(&result, None);
+        }
+    }
+
+    Ok(())
+}
+
+/// Room key of the bind, see [`history::room_key`]
+fn bind_room_key(bind: &req::app::BindInfo) -> String {
+    history::room_key(
+        &bind.area_id,
+        &bind.building_code,
+        &bind.floor_code,
+        &bind.room_code,
+    )
+}
+
+/// Select the bind of room key, defaults to the last one
+fn select_bind(
+    mut binds: Vec<req::app::BindInfo>,
+    room: Option<&str>,
+) -> Result<req::app::BindInfo, error::Error> {
+    match room {
+        Some(room) => match binds.iter().position(|b| bind_room_key(b) == room) {
+            Some(i) => Ok(binds.swap_remove(i)),
+            None => Err(error::Error::Runtime(format!(
+                "Room {} is not bound, see `yxy query bind <UID>`",
+                room
+            ))),
+        },
+        None => binds.pop().ok_or(error::Error::NoBind),
+    }
+}
+
+/// Procedure of query electricity
+///
+/// Reauthorize & retry once if the session expired
+fn query_ele(
+    uid: &str,
+    session: Option<session::Session>,
+    room: Option<&str>,
+    endpoints: &Endpoints,
+    verbose: bool,
+) -> Result<(req::app::ElectricityInfo, session::Session), error::Error> {
+    let session = match session {
+        Some(v) => v,
+        None => app_auth(uid, endpoints, verbose)?,
+    };
+
+    let mut client = client::AuthenticatedClient::with_session(session, endpoints.clone())?;
+    if verbose {
+        client = client.on_refresh(|_| eprintln!("Auth expired, reauthorized."));
+    }
+
+    match client.call(|handler| app_query_ele(handler, room, verbose)) {
+        Ok(v) => Ok((v, client.into_session())),
+        Err(error::Error::AuthExpired) => Err(error::Error::Auth(
+            "Maximum auth retry number reached.".into(),
+        )),
+        Err(e) => Err(e),
     }
 }
 
 /// Authorization sub-procedure
-fn app_auth(id: &str, verbose: bool) -> Result<(String, req::auth::UserInfo), error::Error> {
+fn app_auth(
+    id: &str,
+    endpoints: &Endpoints,
+    verbose: bool,
+) -> Result<session::Session, error::Error> {
     let client = req::init_default_client()?;
 
     if verbose {
         println!("Trying to get oauth code...");
-        let oauth_code = req::auth::get_oauth_code(&client, id)?;
+        let oauth_code = req::auth::get_oauth_code(&client, endpoints, id)?;
         println!("OAuth Code: {}", oauth_code);
 
         println!("Trying to auth...");
-        let (ses, user) = req::auth::authorize(&client, &oauth_code)?;
+        let (ses, user) = req::auth::authorize(&client, endpoints, &oauth_code)?;
         println!("Authorized, the session id is: {}", ses);
 
-        Ok((ses, user))
+        Ok(session::Session::new(id, ses, user))
     } else {
-        let oauth_code = req::auth::get_oauth_code(&client, id)?;
+        let oauth_code = req::auth::get_oauth_code(&client, endpoints, id)?;
 
-        let (ses, user) = req::auth::authorize(&client, &oauth_code)?;
+        let (ses, user) = req::auth::authorize(&client, endpoints, &oauth_code)?;
 
-        Ok((ses, user))
+        Ok(session::Session::new(id, ses, user))
     }
 }
 
 /// Application sub-procedure
-fn app_query_ele(session: &str, verbose: bool) -> Result<req::app::ElectricityInfo, error::Error> {
-    // Init authorized handler
-    let handler = req::Handler::new(session)?;
-
+fn app_query_ele(
+    handler: &req::Handler,
+    room: Option<&str>,
+    verbose: bool,
+) -> Result<req::app::ElectricityInfo, error::Error> {
     // Query Bind Info
     if verbose {
         println!("Querying bind info...");
-        let bind_info = handler.query_bind()?;
+        let bind_info = select_bind(handler.query_binds()?, room)?;
         println!("Bind info: {:?}", bind_info);
 
         // Query Electricity Info
         println!("Query electricity info...");
-        let room_info = req::app::RoomInfo {
-            area_id: &bind_info.area_id,
-            building_code: &bind_info.building_code,
-            floor_code: &bind_info.floor_code,
-            room_code: &bind_info.room_code,
-        };
-        let electricity_info = handler.query_electricity(room_info)?;
+        let electricity_info = handler.query_electricity(bind_info.room_info())?;
         println!("Electricity info: {:?}", electricity_info);
 
         Ok(electricity_info)
     } else {
-        let bind_info = handler.query_bind()?;
+        let bind_info = select_bind(handler.query_binds()?, room)?;
 
         // Query Electricity Info
-        let room_info = req::app::RoomInfo {
-            area_id: &bind_info.area_id,
-            building_code: &bind_info.building_code,
-            floor_code: &bind_info.floor_code,
-            room_code: &bind_info.room_code,
-        };
-        let electricity_info = handler.query_electricity(room_info)?;
+        let electricity_info = handler.query_electricity(bind_info.room_info())?;
 
         Ok(electricity_info)
     }
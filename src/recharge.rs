@@ -0,0 +1,457 @@
+//! Automatic recharge
+//! ------------
+//! Recharge by the rule of account after each query,
+//! keep a local ledger to cap the monthly budget.
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Local};
+use serde::{Deserialize, Serialize};
+
+use yxy::error::Error;
+use yxy::req::app::{ElectricityInfo, RechargeInfo};
+use yxy::req::url::Endpoints;
+
+use crate::conf::{Account, AutoRecharge};
+
+/// Recharge performed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Unix timestamp in seconds
+    pub time: i64,
+    pub uid: String,
+    /// Room key, see [`yxy::history::room_key`]
+    pub room: String,
+    /// Yuan
+    pub amount: f32,
+    /// Empty if not confirmed by the platform
+    pub order_no: String,
+}
+
+/// Ledger file, in JSON Lines format
+///
+/// A recharge in progress or failed to be recorded is kept in the lock file
+/// next to it (`<ledger>.lock`), auto recharge is stopped until it's settled.
+#[derive(Debug)]
+pub struct Ledger {
+    path: PathBuf,
+}
+
+impl Ledger {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Append an entry
+    ///
+    /// Auto create file
+    pub fn append(&self, entry: &LedgerEntry) -> Result<(), Error> {
+        yxy::utils::append_json_line(&self.path, entry)
+    }
+
+    /// Load all entries
+    ///
+    /// Return empty list if the file does not exist
+    pub fn load(&self) -> Result<Vec<LedgerEntry>, Error> {
+        yxy::utils::load_json_lines(&self.path)
+    }
+
+    /// Check the ledger can be written before paying
+    ///
+    /// Auto create file
+    pub fn check_writable(&self) -> Result<(), Error> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        Ok(())
+    }
+
+    pub fn lock_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        path.into()
+    }
+
+    /// Keep the recharge in the lock file
+    pub fn lock(&self, entry: &LedgerEntry) -> Result<(), Error> {
+        yxy::utils::file_write(
+            &self.lock_path().to_string_lossy(),
+            &serde_json::to_string(entry)?,
+        )
+    }
+
+    /// Remove the lock file
+    pub fn unlock(&self) -> Result<(), Error> {
+        match std::fs::remove_file(self.lock_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the recharge of the lock file to the ledger, and remove the lock file
+    ///
+    /// Fail on a recharge not confirmed by the platform, left to be checked by hand.
+    pub fn settle(&self) -> Result<(), Error> {
+        let entry: LedgerEntry = match std::fs::read_to_string(self.lock_path()) {
+            Ok(v) => serde_json::from_str(&v)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if entry.order_no.is_empty() {
+            return Err(Error::Runtime(format!(
+                "Unconfirmed recharge of ￥{} in {}, check the order, add it to the ledger and remove the lock file",
+                entry.amount,
+                self.lock_path().display()
+            )));
+        }
+
+        // Written before failing to unlock
+        if !self.load()?.iter().any(|v| v.order_no == entry.order_no) {
+            self.append(&entry)?;
+        }
+        self.unlock()
+    }
+}
+
+/// Check the opt-in of real recharge orders
+///
+/// The recharge API contract is not verified against the real platform.
+pub fn check_opt_in(allowed: bool) -> Result<(), Error> {
+    if allowed {
+        return Ok(());
+    }
+    Err(Error::Runtime(
+        "The recharge API is not verified against the real platform, \
+set `allow_unverified_recharge: true` in conf to send real orders"
+            .into(),
+    ))
+}
+
+/// Yuan spent by the uid in the local month of `now`
+pub fn spent_in_month(entries: &[LedgerEntry], uid: &str, now: i64) -> f32 {
+    let month = |t: i64| yxy::history::local_time(t).map(|d| (d.year(), d.month()));
+    let this_month = month(now);
+    entries
+        .iter()
+        .filter(|e| e.uid == uid && this_month.is_some() && month(e.time) == this_month)
+        .map(|e| e.amount)
+        .sum()
+}
+
+/// Decision of the rule
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// Surplus is enough
+    Skip,
+    /// Recharge the yuan
+    Recharge(f32),
+    /// Recharge required, but the monthly budget would be exceeded
+    OverBudget { spent: f32 },
+}
+
+/// Evaluate the rule by surplus and yuan spent this month
+pub fn evaluate(rule: &AutoRecharge, soc: f32, spent: f32) -> Decision {
+    if soc >= rule.threshold {
+        Decision::Skip
+    } else if spent + rule.amount > rule.monthly_limit {
+        Decision::OverBudget { spent }
+    } else {
+        Decision::Recharge(rule.amount)
+    }
+}
+
+/// Auto recharge procedure
+///
+/// Push message of what it did, return the refreshed electricity info if recharged.
+/// Errors are reported and skipped, except a paid recharge failed to be written to the ledger:
+/// it's returned, and auto recharge is stopped until the ledger is written.
+///
+/// Nothing is paid without `allowed`, see [`check_opt_in`].
+pub fn auto_recharge(
+    rule: &AutoRecharge,
+    account: &Account,
+    session: &str,
+    info: &ElectricityInfo,
+    endpoints: &Endpoints,
+    allowed: bool,
+    verbose: bool,
+) -> Result<Option<ElectricityInfo>, Error> {
+    let ledger = Ledger::new(&rule.ledger_file);
+    if let Err(e) = ledger.settle() {
+        return Err(Error::Runtime(format!(
+            "Auto recharge stopped, fail to settle the last recharge: {}",
+            e
+        )));
+    }
+
+    let now = Local::now().timestamp();
+    let spent = match ledger.load() {
+        Ok(v) => spent_in_month(&v, &account.uid, now),
+        Err(e) => {
+            eprintln!("Fail to load the recharge ledger: {}", e);
+            return Ok(None);
+        }
+    };
+
+    let mut ledger_error = None;
+    let (title, desp, result) = match evaluate(rule, info.soc, spent) {
+        Decision::Skip => {
+            if verbose {
+                println!("Auto recharge skipped, surplus: {} kW·h", info.soc);
+            }
+            return Ok(None);
+        }
+        Decision::OverBudget { spent } => {
+            eprintln!(
+                "Auto recharge refused, monthly limit ￥{} exceeded (spent ￥{})",
+                rule.monthly_limit, spent
+            );
+            (
+                format!("Auto recharge refused: {}", info.soc),
+                format!(
+                    "Surplus of **{}** is **{}** kW·h, below **{}** kW·h.\n\nRecharging ￥{} would exceed the monthly limit ￥{} (spent ￥{}).",
+                    info.display_room_name,
+                    info.soc,
+                    rule.threshold,
+                    rule.amount,
+                    rule.monthly_limit,
+                    spent
+                ),
+                None,
+            )
+        }
+        Decision::Recharge(amount) => {
+            match check_opt_in(allowed).and_then(|_| {
+                recharge(
+                    rule, account, session, info, amount, endpoints, &ledger, now,
+                )
+            }) {
+                Ok(recharged) => {
+                    println!(
+                        "Auto recharged ￥{}, order no: {}",
+                        amount, recharged.order_no
+                    );
+                    let mut desp = format!(
+                        "Surplus of **{}** was **{}** kW·h, below **{}** kW·h.\n\nRecharged **￥{}**, order no: {}, spent ￥{} of ￥{} this month.",
+                        info.display_room_name,
+                        info.soc,
+                        rule.threshold,
+                        amount,
+                        recharged.order_no,
+                        spent + amount,
+                        rule.monthly_limit
+                    );
+                    if let Some(e) = &recharged.ledger_error {
+                        desp.push_str(&format!(
+                            "\n\n**Fail to write the recharge ledger: {}**, auto recharge is stopped until it's written.",
+                            e
+                        ));
+                    }
+                    ledger_error = recharged.ledger_error;
+                    (
+                        format!("Auto recharged: ￥{}", amount),
+                        desp,
+                        recharged.refreshed,
+                    )
+                }
+                Err(e) => {
+                    eprintln!("Auto recharge error: {}", e);
+                    (
+                        format!("Auto recharge failed: {}", info.soc),
+                        format!(
+                            "Fail to recharge ￥{} for **{}**: {}",
+                            amount, info.display_room_name, e
+                        ),
+                        None,
+                    )
+                }
+            }
+        }
+    };
+
+    if let Some(sc) = &account.server_chan {
+        if let Err(e) = yxy::req::notice::push_message(endpoints, &sc.key, &title, &desp) {
+            eprintln!("Fail to push message: {}", e);
+        }
+    }
+
+    match ledger_error {
+        Some(e) => Err(Error::Runtime(format!(
+            "Recharged, but fail to write the recharge ledger: {}",
+            e
+        ))),
+        None => Ok(result),
+    }
+}
+
+/// Paid recharge
+struct Recharged {
+    order_no: String,
+    refreshed: Option<ElectricityInfo>,
+    /// The entry is kept in the lock file, see [`Ledger::settle`]
+    ledger_error: Option<Error>,
+}
+
+/// Submit the order and write the ledger
+#[allow(clippy::too_many_arguments)]
+fn recharge(
+    rule: &AutoRecharge,
+    account: &Account,
+    session: &str,
+    info: &ElectricityInfo,
+    amount: f32,
+    endpoints: &Endpoints,
+    ledger: &Ledger,
+    now: i64,
+) -> Result<Recharged, Error> {
+    let top_up_type = match &rule.top_up_type {
+        Some(t) => info
+            .top_up_type_list
+            .iter()
+            .find(|v| &v.cztype == t || &v.mdname == t),
+        None => info.top_up_type_list.first(),
+    }
+    .ok_or_else(|| Error::Runtime("No such top-up type".into()))?;
+
+    let handler = yxy::req::Handler::with_endpoints(session, endpoints.clone())?;
+    let recharge_info = RechargeInfo::new(info, top_up_type, amount)?;
+
+    let mut entry = LedgerEntry {
+        time: now,
+        uid: account.uid.clone(),
+        room: yxy::history::room_key(
+            &info.area_id,
+            &info.building_code,
+            &info.floor_code,
+            &info.room_code,
+        ),
+        amount,
+        order_no: String::new(),
+    };
+    // Never pay without a writable ledger and lock file
+    ledger.check_writable()?;
+    ledger.lock(&entry)?;
+
+    let order = match handler.recharge(&recharge_info) {
+        Ok(v) => v,
+        Err(e) => {
+            ledger.unlock()?;
+            return Err(e);
+        }
+    };
+
+    entry.order_no = order.order_no.clone();
+    let ledger_error = match ledger.append(&entry).and_then(|_| ledger.unlock()) {
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Fail to write the recharge ledger: {}", e);
+            // Confirmed, settled by the next run
+            if let Err(e) = ledger.lock(&entry) {
+                eprintln!("Fail to write the recharge lock file: {}", e);
+            }
+            Some(e)
+        }
+    };
+
+    let refreshed = match handler.query_electricity(yxy::req::app::RoomInfo {
+        area_id: &info.area_id,
+        building_code: &info.building_code,
+        floor_code: &info.floor_code,
+        room_code: &info.room_code,
+    }) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            eprintln!("Fail to refresh electricity info: {}", e);
+            None
+        }
+    };
+
+    Ok(Recharged {
+        order_no: order.order_no,
+        refreshed,
+        ledger_error,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rule() -> AutoRecharge {
+        AutoRecharge {
+            threshold: 10.0,
+            amount: 20.0,
+            monthly_limit: 50.0,
+            top_up_type: None,
+            ledger_file: String::new(),
+        }
+    }
+
+    fn entry(time: i64, uid: &str, amount: f32) -> LedgerEntry {
+        LedgerEntry {
+            time,
+            uid: uid.into(),
+            room: "1-B01-F03-R301".into(),
+            amount,
+            order_no: "order".into(),
+        }
+    }
+
+    #[test]
+    fn decision() {
+        assert_eq!(evaluate(&rule(), 10.0, 0.0), Decision::Skip);
+        assert_eq!(evaluate(&rule(), 9.0, 30.0), Decision::Recharge(20.0));
+        assert_eq!(
+            evaluate(&rule(), 9.0, 40.0),
+            Decision::OverBudget { spent: 40.0 }
+        );
+    }
+
+    #[test]
+    fn settle() {
+        let dir = std::env::temp_dir().join(format!("yxy-ledger-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let ledger = Ledger::new(dir.join("recharge.jsonl"));
+        ledger.settle().unwrap();
+
+        // Unconfirmed, kept for checking by hand
+        let mut pending = entry(1, "a", 10.0);
+        pending.order_no = String::new();
+        ledger.lock(&pending).unwrap();
+        assert!(ledger.settle().is_err());
+        assert!(ledger.lock_path().exists());
+
+        // Confirmed, written once
+        ledger.lock(&entry(1, "a", 10.0)).unwrap();
+        ledger.settle().unwrap();
+        assert!(!ledger.lock_path().exists());
+        ledger.lock(&entry(1, "a", 10.0)).unwrap();
+        ledger.settle().unwrap();
+        assert_eq!(ledger.load().unwrap(), [entry(1, "a", 10.0)]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn monthly_spent() {
+        let time = |m, d| {
+            let t = chrono::NaiveDate::from_ymd_opt(2022, m, d)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            Local.from_local_datetime(&t).unwrap()
+        };
+        let (now, last_month) = (time(10, 15), time(9, 30));
+        let entries = vec![
+            entry(now.timestamp() - 86400, "a", 20.0),
+            entry(now.timestamp(), "a", 10.0),
+            entry(now.timestamp(), "b", 10.0),
+            entry(last_month.timestamp(), "a", 20.0),
+        ];
+        assert_eq!(spent_in_month(&entries, "a", now.timestamp()), 30.0);
+    }
+}
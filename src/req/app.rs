@@ -1,20 +1,12 @@
 //! Application APIs
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::{check_response, url, Handler};
+use super::{check_response, url, Envelope, Handler};
 use crate::error::Error;
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct QueryBindResponse {
-    pub status_code: i32,
-    pub success: bool,
-    pub total: Option<u32>,
-    pub message: Option<String>,
-    pub rows: Option<Vec<BindInfo>>,
-}
+pub type QueryBindResponse = Envelope<Vec<BindInfo>>;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BindInfo {
     pub id: String,
@@ -35,7 +27,19 @@ pub struct BindInfo {
     pub is_allow_change: u8,
 }
 
-#[derive(Debug, Serialize)]
+impl BindInfo {
+    /// Room of the bind, to query electricity for
+    pub fn room_info(&self) -> RoomInfo<'_> {
+        RoomInfo {
+            area_id: &self.area_id,
+            building_code: &self.building_code,
+            floor_code: &self.floor_code,
+            room_code: &self.room_code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RoomInfo<'a> {
     pub area_id: &'a str,
@@ -44,17 +48,54 @@ pub struct RoomInfo<'a> {
     pub room_code: &'a str,
 }
 
+/// Location of the area -> building -> floor -> room hierarchy
+///
+/// Query the children of the location, empty for areas
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub building_code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floor_code: Option<&'a str>,
+}
+
+pub type QueryListResponse<T> = Envelope<Vec<T>>;
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct QueryElResponse {
-    pub status_code: i32,
-    pub success: bool,
-    pub message: String,
-    pub data: Option<ElectricityInfo>,
+pub struct Area {
+    pub id: String,
+    pub area_name: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct Building {
+    pub building_code: String,
+    pub building_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Floor {
+    pub floor_code: String,
+    pub floor_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Room {
+    pub room_code: String,
+    pub room_name: String,
+}
+
+pub type QueryElResponse = Envelope<ElectricityInfo>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ElectricityInfo {
     pub school_code: String,
     pub area_id: String,
@@ -73,7 +114,7 @@ pub struct ElectricityInfo {
     pub top_up_type_list: Vec<ElTopUpType>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ElSurplus {
     pub surplus: f32,
@@ -86,70 +127,186 @@ pub struct ElSurplus {
     pub room_status: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ElTopUpType {
     pub mdname: String,
     pub cztype: String,
 }
 
+/// Top-up order to submit
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RechargeInfo<'a> {
+    #[serde(flatten)]
+    pub room: RoomInfo<'a>,
+    /// [`ElTopUpType::cztype`]
+    pub cztype: &'a str,
+    /// Amount in yuan, with 2 decimal places
+    pub amount: String,
+}
+
+impl<'a> RechargeInfo<'a> {
+    /// Top-up the room of electricity info by the top-up type
+    pub fn new(
+        info: &'a ElectricityInfo,
+        top_up_type: &'a ElTopUpType,
+        amount: f32,
+    ) -> Result<Self, Error> {
+        if !(amount.is_finite() && amount >= 0.01) {
+            return Err(Error::Runtime(format!("Bad recharge amount: {}", amount)));
+        }
+        Ok(Self {
+            room: RoomInfo {
+                area_id: &info.area_id,
+                building_code: &info.building_code,
+                floor_code: &info.floor_code,
+                room_code: &info.room_code,
+            },
+            cztype: &top_up_type.cztype,
+            amount: format!("{:.2}", amount),
+        })
+    }
+}
+
+pub type RechargeResponse = Envelope<RechargeOrder>;
+
+/// Created top-up order
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RechargeOrder {
+    pub order_no: String,
+    pub amount: String,
+}
+
 impl Handler {
     /// Query Bind infos
     ///
-    /// Only return one bind info from list
+    /// Only return the last bind info from list, see [`Handler::query_binds`]
     pub fn query_bind(&self) -> Result<BindInfo, Error> {
-        let form = vec![("bindType", "3")];
+        take_bind(self.query_binds()?)
+    }
+
+    /// Query all bind infos
+    pub fn query_binds(&self) -> Result<Vec<BindInfo>, Error> {
         let mut resp = self
             .client
-            .post(url::application::QUERY_BIND)
-            .form(&form)
+            .post(self.endpoints.application_url(url::application::QUERY_BIND))
+            .form(&QUERY_BIND_FORM)
             .send()?;
         check_response(&mut resp)?;
-        let resp_ser: QueryBindResponse = resp.json()?;
-        if resp_ser.success == false {
-            if resp_ser.status_code == 204 {
-                return Err(Error::AuthExpired);
-            }
-            return Err(Error::Runtime(format!(
-                "Fail to query bind: {}",
-                resp_ser.message.unwrap()
-            )));
-        }
-
-        // Take data
-        if let Some(mut bind_info) = resp_ser.rows {
-            Ok(match bind_info.pop() {
-                Some(v) => v,
-                None => return Err(Error::NoBind),
-            })
-        } else {
-            Err(Error::NoBind)
-        }
+        take_binds(&resp.text()?)
     }
 
     pub fn query_electricity(&self, info: RoomInfo) -> Result<ElectricityInfo, Error> {
         let mut resp = self
             .client
-            .post(url::application::QUERY_ELECTRICITY)
+            .post(
+                self.endpoints
+                    .application_url(url::application::QUERY_ELECTRICITY),
+            )
             .json(&info)
             .send()?;
         check_response(&mut resp)?;
-        let resp_ser: QueryElResponse = resp.json()?;
-
-        if resp_ser.success == false {
-            if resp_ser.status_code == 204 {
-                return Err(Error::AuthExpired);
-            }
-            return Err(Error::Runtime(format!(
-                "Fail to query electricity: {}",
-                resp_ser.message
-            )));
-        }
+        QueryElResponse::decode_session(url::application::QUERY_ELECTRICITY, &resp.text()?)?.data()
+    }
 
-        if let Some(v) = resp_ser.data {
-            Ok(v)
-        } else {
-            Err(Error::EmptyResp)
-        }
+    /// Create a top-up order of the room, paid by the account balance
+    ///
+    /// **Unverified**: the path, request body and response shape are not confirmed
+    /// by platform source or captured traffic, only tested against the mock platform.
+    /// It spends real money, callers should require an explicit opt-in.
+    pub fn recharge(&self, info: &RechargeInfo) -> Result<RechargeOrder, Error> {
+        let mut resp = self
+            .client
+            .post(self.endpoints.application_url(url::application::RECHARGE))
+            .json(info)
+            .send()?;
+        check_response(&mut resp)?;
+        RechargeResponse::decode_session(url::application::RECHARGE, &resp.text()?)?.data()
+    }
+
+    /// Query areas of the school
+    pub fn query_areas(&self) -> Result<Vec<Area>, Error> {
+        self.query_list(url::application::QUERY_AREA, &Location::default())
+    }
+
+    /// Query buildings of the area
+    pub fn query_buildings(&self, area_id: &str) -> Result<Vec<Building>, Error> {
+        self.query_list(
+            url::application::QUERY_BUILDING,
+            &Location {
+                area_id: Some(area_id),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Query floors of the building
+    pub fn query_floors(&self, area_id: &str, building_code: &str) -> Result<Vec<Floor>, Error> {
+        self.query_list(
+            url::application::QUERY_FLOOR,
+            &Location {
+                area_id: Some(area_id),
+                building_code: Some(building_code),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Query rooms of the floor
+    pub fn query_rooms(
+        &self,
+        area_id: &str,
+        building_code: &str,
+        floor_code: &str,
+    ) -> Result<Vec<Room>, Error> {
+        self.query_list(
+            url::application::QUERY_ROOM,
+            &Location {
+                area_id: Some(area_id),
+                building_code: Some(building_code),
+                floor_code: Some(floor_code),
+            },
+        )
+    }
+
+    fn query_list<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        location: &Location,
+    ) -> Result<Vec<T>, Error> {
+        let mut resp = self
+            .client
+            .post(self.endpoints.application_url(path))
+            .json(location)
+            .send()?;
+        check_response(&mut resp)?;
+        take_list(path, &resp.text()?)
     }
 }
+
+/// Form data of query bind request
+pub(crate) const QUERY_BIND_FORM: [(&str, &str); 1] = [("bindType", "3")];
+
+/// Take the bind infos out of query bind response
+pub(crate) fn take_binds(body: &str) -> Result<Vec<BindInfo>, Error> {
+    let resp = QueryBindResponse::decode_session(url::application::QUERY_BIND, body)?;
+
+    match resp.data {
+        Some(v) if !v.is_empty() => Ok(v),
+        _ => Err(Error::NoBind),
+    }
+}
+
+/// Take the last one of bind infos
+pub(crate) fn take_bind(mut binds: Vec<BindInfo>) -> Result<BindInfo, Error> {
+    binds.pop().ok_or(Error::NoBind)
+}
+
+/// Take the items out of query list response
+pub(crate) fn take_list<T: DeserializeOwned>(path: &str, body: &str) -> Result<Vec<T>, Error> {
+    let resp = QueryListResponse::<T>::decode_session(path, body)?;
+
+    Ok(resp.data.unwrap_or_default())
+}
@@ -0,0 +1,134 @@
+//! Application APIs (async)
+use serde::de::DeserializeOwned;
+
+use super::{check_response, Handler};
+use crate::error::Error;
+use crate::req::app::{
+    take_bind, take_binds, take_list, Area, BindInfo, Building, ElectricityInfo, Floor, Location,
+    QueryElResponse, RechargeInfo, RechargeOrder, RechargeResponse, Room, RoomInfo,
+    QUERY_BIND_FORM,
+};
+use crate::req::url;
+
+impl Handler {
+    /// Query Bind infos
+    ///
+    /// Only return the last bind info from list, see [`Handler::query_binds`]
+    pub async fn query_bind(&self) -> Result<BindInfo, Error> {
+        take_bind(self.query_binds().await?)
+    }
+
+    /// Query all bind infos
+    pub async fn query_binds(&self) -> Result<Vec<BindInfo>, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.application_url(url::application::QUERY_BIND))
+            .form(&QUERY_BIND_FORM)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        take_binds(&resp.text().await?)
+    }
+
+    pub async fn query_electricity(&self, info: RoomInfo<'_>) -> Result<ElectricityInfo, Error> {
+        let resp = self
+            .client
+            .post(
+                self.endpoints
+                    .application_url(url::application::QUERY_ELECTRICITY),
+            )
+            .json(&info)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        QueryElResponse::decode_session(url::application::QUERY_ELECTRICITY, &resp.text().await?)?
+            .data()
+    }
+
+    /// Create a top-up order of the room, paid by the account balance
+    ///
+    /// **Unverified**: the path, request body and response shape are not confirmed
+    /// by platform source or captured traffic, only tested against the mock platform.
+    /// It spends real money, callers should require an explicit opt-in.
+    pub async fn recharge(&self, info: &RechargeInfo<'_>) -> Result<RechargeOrder, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.application_url(url::application::RECHARGE))
+            .json(info)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        RechargeResponse::decode_session(url::application::RECHARGE, &resp.text().await?)?.data()
+    }
+
+    /// Query areas of the school
+    pub async fn query_areas(&self) -> Result<Vec<Area>, Error> {
+        self.query_list(url::application::QUERY_AREA, &Location::default())
+            .await
+    }
+
+    /// Query buildings of the area
+    pub async fn query_buildings(&self, area_id: &str) -> Result<Vec<Building>, Error> {
+        self.query_list(
+            url::application::QUERY_BUILDING,
+            &Location {
+                area_id: Some(area_id),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Query floors of the building
+    pub async fn query_floors(
+        &self,
+        area_id: &str,
+        building_code: &str,
+    ) -> Result<Vec<Floor>, Error> {
+        self.query_list(
+            url::application::QUERY_FLOOR,
+            &Location {
+                area_id: Some(area_id),
+                building_code: Some(building_code),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Query rooms of the floor
+    pub async fn query_rooms(
+        &self,
+        area_id: &str,
+        building_code: &str,
+        floor_code: &str,
+    ) -> Result<Vec<Room>, Error> {
+        self.query_list(
+            url::application::QUERY_ROOM,
+            &Location {
+                area_id: Some(area_id),
+                building_code: Some(building_code),
+                floor_code: Some(floor_code),
+            },
+        )
+        .await
+    }
+
+    async fn query_list<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        location: &Location<'_>,
+    ) -> Result<Vec<T>, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.application_url(path))
+            .json(location)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+        take_list(path, &resp.text().await?)
+    }
+}
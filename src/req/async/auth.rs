@@ -0,0 +1,57 @@
+//! Authorization APIs (async)
+use std::collections::HashMap;
+
+use reqwest::Client;
+
+use super::check_response;
+use crate::error::Error;
+use crate::req::auth::{extract_code, oauth_query, parse_authorize, UserInfo, SESSION_KEY};
+use crate::req::url::{self, Endpoints};
+
+pub async fn get_oauth_code(
+    client: &Client,
+    endpoints: &Endpoints,
+    id: &str,
+) -> Result<String, Error> {
+    let response = client
+        .get(endpoints.auth_url(url::auth::OAUTH_URL))
+        .query(&oauth_query(endpoints, id))
+        .send()
+        .await?;
+    let response = check_response(response).await?;
+
+    let text = response.text().await?;
+
+    match extract_code(&text) {
+        Some(t) => Ok(t),
+        None => Err(Error::Runtime("No OAuth code response".into())),
+    }
+}
+
+/// Authorize the handler and fetch user infos
+pub async fn authorize(
+    client: &Client,
+    endpoints: &Endpoints,
+    code: &str,
+) -> Result<(String, UserInfo), Error> {
+    // Form data
+    let mut params = HashMap::new();
+    params.insert("code", code);
+
+    let response = client
+        .post(endpoints.application_url(url::application::GET_USER_FOR_AUTHORIZE))
+        .form(&params)
+        .send()
+        .await?;
+    let response = check_response(response).await?;
+
+    // get session
+    let session = response
+        .cookies()
+        .find(|x| x.name() == SESSION_KEY)
+        .map(|v| v.value().to_string());
+
+    let resp = response.text().await?;
+
+    parse_authorize(session, &resp)
+}
@@ -0,0 +1,193 @@
+//! Simulate app login requests (async)
+use std::collections::HashMap;
+
+use reqwest::Client;
+
+use super::check_response;
+use crate::error::Error;
+use crate::req::login::{
+    app_sim_headers, app_sim_user_agent, basic_request_body, captcha_image_body, gen_device_id,
+    login_body, parse_login, password_login_body, security_token_body, silent_login_body,
+    take_verification_result, verification_code_body, BasicResponse, CaptchaImage, LoginResponse,
+    SecurityTokenResponse,
+};
+use crate::req::url::{self, Endpoints};
+
+pub struct LoginHandler {
+    pub phone_num: String,
+    pub device_id: String,
+    /// App login token of the saved profile
+    pub token: Option<String>,
+    client: Client,
+    endpoints: Endpoints,
+}
+
+impl LoginHandler {
+    pub fn new(phone_num: String) -> Result<Self, Error> {
+        let device_id = gen_device_id();
+
+        Ok(Self {
+            phone_num,
+            client: init_app_sim_client(&device_id)?,
+            device_id,
+            token: None,
+            endpoints: Endpoints::default(),
+        })
+    }
+
+    pub fn init(phone_num: &str, device_id: &str) -> Result<Self, Error> {
+        Self::init_with_endpoints(phone_num, device_id, Endpoints::default())
+    }
+
+    /// Init handler requesting the given endpoints
+    pub fn init_with_endpoints(
+        phone_num: &str,
+        device_id: &str,
+        endpoints: Endpoints,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            phone_num: phone_num.to_string(),
+            device_id: device_id.to_string(),
+            token: None,
+            client: init_app_sim_client(device_id)?,
+            endpoints,
+        })
+    }
+
+    /// Init handler by the saved profile, reusing its device id and app token
+    pub fn from_profile(profile: &LoginResponse, endpoints: Endpoints) -> Result<Self, Error> {
+        let mut handler =
+            Self::init_with_endpoints(&profile.mobile_phone, &profile.device_id, endpoints)?;
+        handler.token = Some(profile.token.clone());
+
+        Ok(handler)
+    }
+
+    /// Init general request body
+    pub fn get_basic_request_body(&self) -> HashMap<&str, serde_json::Value> {
+        basic_request_body(&self.device_id)
+    }
+
+    /// Return security token & level
+    pub async fn get_security_token(&self) -> Result<SecurityTokenResponse, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::GET_SECURITY_TOKEN))
+            .json(&security_token_body(&self.device_id))
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        BasicResponse::decode(url::app::GET_SECURITY_TOKEN, &resp.text().await?)?.data()
+    }
+
+    /// Get image captcha
+    /// ------------
+    /// Return image captcha base64 string
+    pub async fn get_captcha_image(&self, security_token: &str) -> Result<String, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::GET_IMAGE_CAPTCHA))
+            .json(&captcha_image_body(&self.device_id, security_token))
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        BasicResponse::decode(url::app::GET_IMAGE_CAPTCHA, &resp.text().await?)?.data()
+    }
+
+    /// Get image captcha, decoded
+    pub async fn get_captcha(&self, security_token: &str) -> Result<CaptchaImage, Error> {
+        CaptchaImage::decode(&self.get_captcha_image(security_token).await?)
+    }
+
+    /// Request to send login verification code sms
+    pub async fn send_verification_code(
+        &self,
+        security_token: &str,
+        captcha: Option<&str>,
+    ) -> Result<bool, Error> {
+        let body =
+            verification_code_body(&self.device_id, &self.phone_num, security_token, captcha)?;
+
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::SEND_VERIFICATION_CODE))
+            .json(&body)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        take_verification_result(&resp.text().await?)
+    }
+
+    /// Do login with verification code
+    pub async fn do_login(&self, code: &str) -> Result<LoginResponse, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_CODE))
+            .json(&login_body(&self.device_id, &self.phone_num, code))
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        parse_login(url::app::DO_LOGIN_BY_CODE, &resp.text().await?)
+    }
+
+    /// Return Base64 public key of password encryption
+    pub async fn get_public_key(&self) -> Result<String, Error> {
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::GET_PUBLIC_KEY))
+            .json(&basic_request_body(&self.device_id))
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        BasicResponse::decode(url::app::GET_PUBLIC_KEY, &resp.text().await?)?.data()
+    }
+
+    /// Do login with password, instead of verification code
+    pub async fn do_login_by_password(&self, password: &str) -> Result<LoginResponse, Error> {
+        let public_key = self.get_public_key().await?;
+        let body = password_login_body(&self.device_id, &self.phone_num, password, &public_key)?;
+
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_PASSWORD))
+            .json(&body)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        parse_login(url::app::DO_LOGIN_BY_PASSWORD, &resp.text().await?)
+    }
+
+    /// Silent login by the app login token of saved profile
+    /// ------------
+    /// Return [`Error::TokenRevoked`] if the token is no longer valid
+    pub async fn do_silent_login(&self) -> Result<LoginResponse, Error> {
+        let body = silent_login_body(&self.device_id, &self.phone_num, self.token.as_deref())?;
+
+        let resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_SILENT))
+            .json(&body)
+            .send()
+            .await?;
+        let resp = check_response(resp).await?;
+
+        parse_login(url::app::DO_LOGIN_BY_SILENT, &resp.text().await?)
+    }
+}
+
+/// Init App simulated client (async)
+pub fn init_app_sim_client(device_id: &str) -> Result<Client, Error> {
+    let result = Client::builder()
+        .connect_timeout(std::time::Duration::new(5, 0))
+        .user_agent(app_sim_user_agent(device_id))
+        .default_headers(app_sim_headers())
+        .build()?;
+
+    Ok(result)
+}
@@ -0,0 +1,110 @@
+//! Asynchronous requests
+//! ------------
+//! Non-blocking variant of the [`crate::req`] APIs,
+//! enabled by the `async` feature.
+//!
+//! Data types are shared with the blocking APIs.
+use std::time::Duration;
+
+use reqwest::{Client, Response};
+
+use super::{bad_response, get_default_headers, session_jar, url::Endpoints, USER_AGENT};
+use crate::error::Error;
+
+pub mod app;
+pub mod auth;
+pub mod login;
+pub mod notice;
+
+/// Init default reqwest (async) client.
+pub fn init_default_client() -> Result<Client, Error> {
+    let result = Client::builder()
+        .connect_timeout(Duration::new(5, 0))
+        .user_agent(USER_AGENT)
+        .default_headers(get_default_headers())
+        .build()?;
+
+    Ok(result)
+}
+
+/// Session handle
+#[derive(Debug)]
+pub struct Handler {
+    client: Client,
+    endpoints: Endpoints,
+}
+
+impl Handler {
+    pub fn new(session: &str) -> Result<Self, Error> {
+        Self::with_endpoints(session, Endpoints::default())
+    }
+
+    /// Init handler requesting the given endpoints
+    pub fn with_endpoints(session: &str, endpoints: Endpoints) -> Result<Self, Error> {
+        Ok(Self {
+            client: Client::builder()
+                .connect_timeout(Duration::new(5, 0))
+                .user_agent(USER_AGENT)
+                .default_headers(get_default_headers())
+                .cookie_provider(session_jar(session, &endpoints)?)
+                .build()?,
+            endpoints,
+        })
+    }
+}
+
+async fn check_response(res: Response) -> Result<Response, Error> {
+    if !res.status().is_success() {
+        let status = res.status();
+        let endpoint = res.url().path().to_string();
+        let text = res.text().await?;
+        return Err(bad_response(&endpoint, status, &text));
+    }
+
+    Ok(res)
+}
+
+/// Authorization
+///
+/// Async version of [`crate::auth`]
+pub async fn auth(uid: &str) -> Result<(String, super::auth::UserInfo), Error> {
+    auth_with_endpoints(uid, &Endpoints::default()).await
+}
+
+/// Authorization with the given endpoints
+pub async fn auth_with_endpoints(
+    uid: &str,
+    endpoints: &Endpoints,
+) -> Result<(String, super::auth::UserInfo), Error> {
+    let client = init_default_client()?;
+
+    let oauth_code = auth::get_oauth_code(&client, endpoints, uid).await?;
+
+    let (ses, user) = auth::authorize(&client, endpoints, &oauth_code).await?;
+
+    Ok((ses, user))
+}
+
+/// Query electricity
+///
+/// Async version of [`crate::query_ele`]
+pub async fn query_ele(session: &str) -> Result<super::app::ElectricityInfo, Error> {
+    query_ele_with_endpoints(session, Endpoints::default()).await
+}
+
+/// Query electricity with the given endpoints
+pub async fn query_ele_with_endpoints(
+    session: &str,
+    endpoints: Endpoints,
+) -> Result<super::app::ElectricityInfo, Error> {
+    // Init authorized handler
+    let handler = Handler::with_endpoints(session, endpoints)?;
+
+    // Query Bind Info
+    let bind_info = handler.query_bind().await?;
+
+    // Query Electricity Info
+    let electricity_info = handler.query_electricity(bind_info.room_info()).await?;
+
+    Ok(electricity_info)
+}
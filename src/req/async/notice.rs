@@ -0,0 +1,22 @@
+//! ServerChan message push service (async)
+use reqwest::Client;
+
+use crate::error::Error;
+use crate::req::notice::take_push_result;
+use crate::req::url::Endpoints;
+
+pub async fn push_message(
+    endpoints: &Endpoints,
+    key: &str,
+    title: &str,
+    desp: &str,
+) -> Result<(String, String), Error> {
+    let client = Client::new();
+    let resp = client
+        .post(endpoints.server_chan_url(key))
+        .form(&[("title", title), ("desp", desp)])
+        .send()
+        .await?;
+
+    take_push_result(&resp.text().await?)
+}
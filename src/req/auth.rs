@@ -1,28 +1,21 @@
 //! Authorization APIs
 use std::{collections::HashMap, io::Read};
 
-use reqwest::{blocking::Client, cookie::Cookie};
-use serde::Deserialize;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
 
-use super::{check_response, url};
+use super::{check_response, url, url::Endpoints, Envelope};
 use crate::error::Error;
 
 /// A constant value
-const APPID: &'static str = "1810181825222034";
-pub const SESSION_KEY: &'static str = "shiroJID";
+const APPID: &str = "1810181825222034";
+pub const SESSION_KEY: &str = "shiroJID";
 
 /// Authorize API response definition
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct AuthResponse {
-    // status_code: i32,
-    message: String,
-    success: bool,
-    data: Option<UserInfo>,
-}
+type AuthResponse = Envelope<UserInfo>;
 
 /// User info provided by platform
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserInfo {
     pub id: String,
@@ -49,11 +42,11 @@ pub struct UserInfo {
 /// callbackUrl = "https://xxx.com";
 /// ```
 /// If matched, return the code value
-fn extract_code(text: &str) -> Option<String> {
+pub(crate) fn extract_code(text: &str) -> Option<String> {
     match text.find("var code = ") {
         Some(i) => {
-            let pat = &text[i + 12..i + 12 + 32];
-            if pat.contains("\"") {
+            let pat = text.get(i + 12..i + 12 + 32)?;
+            if pat.contains('"') {
                 None
             } else {
                 Some(pat.into())
@@ -63,16 +56,10 @@ fn extract_code(text: &str) -> Option<String> {
     }
 }
 
-pub fn get_oauth_code(client: &Client, id: &str) -> Result<String, Error> {
+pub fn get_oauth_code(client: &Client, endpoints: &Endpoints, id: &str) -> Result<String, Error> {
     let mut response = client
-        .get(url::auth::OAUTH_URL)
-        .query(&[
-            ("bindSkip", "1"),
-            ("authType", "2"),
-            ("appid", APPID),
-            ("callbackUrl", url::application::BASE_URL),
-            ("unionid", id),
-        ])
+        .get(endpoints.auth_url(url::auth::OAUTH_URL))
+        .query(&oauth_query(endpoints, id))
         .send()?;
     check_response(&mut response)?;
 
@@ -84,50 +71,55 @@ pub fn get_oauth_code(client: &Client, id: &str) -> Result<String, Error> {
     }
 }
 
+/// Query parameters of OAuth code request
+pub(crate) fn oauth_query<'a>(endpoints: &'a Endpoints, id: &'a str) -> [(&'a str, &'a str); 5] {
+    [
+        ("bindSkip", "1"),
+        ("authType", "2"),
+        ("appid", APPID),
+        ("callbackUrl", &endpoints.application),
+        ("unionid", id),
+    ]
+}
+
 /// Authorize the handler and fetch user infos
-pub fn authorize(client: &Client, code: &str) -> Result<(String, UserInfo), Error> {
+pub fn authorize(
+    client: &Client,
+    endpoints: &Endpoints,
+    code: &str,
+) -> Result<(String, UserInfo), Error> {
     // Form data
     let mut params = HashMap::new();
     params.insert("code", code);
 
     let mut response = client
-        .post(url::application::GET_USER_FOR_AUTHORIZE)
+        .post(endpoints.application_url(url::application::GET_USER_FOR_AUTHORIZE))
         .form(&params)
         .send()?;
     check_response(&mut response)?;
 
-    let cookies: Vec<Cookie> = response.cookies().collect();
-
     // get session
-    match cookies.iter().find(|x| x.name() == SESSION_KEY) {
-        Some(v) => {
-            let session = v.value().to_string();
-            let mut resp = String::new();
-            response.read_to_string(&mut resp)?;
-            let resp_ser: AuthResponse = match serde_json::from_str(&resp) {
-                Ok(v) => v,
-                Err(e) => return Err(Error::Runtime(format!("Parsing error: {e}\nData: {resp}"))),
-            };
-            if resp_ser.success == false {
-                return Err(Error::Runtime(format!(
-                    "Authorize failed: {}",
-                    resp_ser.message
-                )));
-            }
-            match resp_ser.data {
-                Some(v) => Ok((session, v)),
-                None => Err(Error::Runtime(format!(
-                    "Authorize failed: {}",
-                    resp_ser.message
-                ))),
-            }
-        }
-        None => {
-            let resp_ser: AuthResponse = response.json()?;
-            Err(Error::Runtime(format!(
-                "Authorize failed: {}",
-                resp_ser.message
-            )))
-        }
+    let session = response
+        .cookies()
+        .find(|x| x.name() == SESSION_KEY)
+        .map(|v| v.value().to_string());
+
+    let mut resp = String::new();
+    response.read_to_string(&mut resp)?;
+
+    parse_authorize(session, &resp)
+}
+
+/// Parse authorize response with the session id taken from cookies
+pub(crate) fn parse_authorize(
+    session: Option<String>,
+    resp: &str,
+) -> Result<(String, UserInfo), Error> {
+    let resp_ser = AuthResponse::decode(url::application::GET_USER_FOR_AUTHORIZE, resp)?;
+
+    match (session, resp_ser.data) {
+        (Some(session), Some(v)) => Ok((session, v)),
+        (None, _) => Err(Error::Auth("No session cookie".into())),
+        (_, None) => Err(Error::EmptyResp),
     }
 }
@@ -0,0 +1,144 @@
+//! Image captcha solvers
+//! ------------
+//! Configure [`super::login::LoginHandler`] with a solver to answer the image captcha
+//! required by the security token, see [`super::login::LoginHandler::request_verification_code`].
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::login::CaptchaImage;
+use crate::error::Error;
+
+/// Answer the image captcha
+pub trait CaptchaSolver: Send {
+    fn solve(&self, image: &CaptchaImage) -> Result<String, Error>;
+}
+
+impl<F> CaptchaSolver for F
+where
+    F: Fn(&CaptchaImage) -> Result<String, Error> + Send,
+{
+    fn solve(&self, image: &CaptchaImage) -> Result<String, Error> {
+        self(image)
+    }
+}
+
+/// Interactive solver, show the image path and read the answer from stdin
+///
+/// Prints the temp image path and the prompt to the process stdout, and blocks on
+/// the process stdin. Meant for command line programs only, implement
+/// [`CaptchaSolver`] for other frontends.
+#[derive(Debug, Default)]
+pub struct StdinSolver;
+
+impl CaptchaSolver for StdinSolver {
+    fn solve(&self, image: &CaptchaImage) -> Result<String, Error> {
+        println!("Captcha: {}", image.save_temp()?.display());
+        println!("Please input the captcha: ");
+
+        let mut captcha = String::new();
+        std::io::stdin().read_line(&mut captcha)?;
+
+        Ok(captcha.trim().to_string())
+    }
+}
+
+/// External program solver, e.g. an OCR script
+///
+/// The image bytes are piped to the program stdin, with the format extension in
+/// `YXY_CAPTCHA_FORMAT` env. The answer is read from the program stdout.
+#[derive(Debug, Clone)]
+pub struct CommandSolver {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandSolver {
+    pub fn new<S: Into<String>>(program: S, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+        }
+    }
+}
+
+impl CaptchaSolver for CommandSolver {
+    fn solve(&self, image: &CaptchaImage) -> Result<String, Error> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env("YXY_CAPTCHA_FORMAT", image.format.extension())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        // Written aside, so the stdout pipe is drained meanwhile
+        let stdin = child.stdin.take();
+        let output = std::thread::scope(|s| {
+            let writer = s.spawn(|| match stdin {
+                Some(mut stdin) => stdin.write_all(&image.bytes),
+                None => Ok(()),
+            });
+            let output = child.wait_with_output()?;
+            match writer.join() {
+                // Solver may answer without reading the image
+                Ok(Err(e)) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(output),
+            }
+        })?;
+        if !output.status.success() {
+            return Err(Error::Runtime(format!(
+                "Captcha solver `{}` failed: {}",
+                self.program, output.status
+            )));
+        }
+
+        let answer = String::from_utf8(output.stdout)?.trim().to_string();
+        if answer.is_empty() {
+            return Err(Error::Runtime(format!(
+                "Captcha solver `{}` gave no answer",
+                self.program
+            )));
+        }
+
+        Ok(answer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::req::login::ImageFormat;
+
+    #[cfg(unix)]
+    #[test]
+    fn command_solver() -> Result<(), Error> {
+        let image = CaptchaImage {
+            bytes: b"\x89PNG".to_vec(),
+            format: ImageFormat::Png,
+        };
+        let solver = CommandSolver::new(
+            "sh",
+            vec![
+                "-c".into(),
+                "cat > /dev/null; echo \"abcd-$YXY_CAPTCHA_FORMAT\"".into(),
+            ],
+        );
+        assert_eq!(solver.solve(&image)?, "abcd-png");
+
+        // Stdin not read, with an image beyond the pipe buffer
+        let large = CaptchaImage {
+            bytes: vec![0; 1 << 20],
+            format: ImageFormat::Png,
+        };
+        let solver = CommandSolver::new("sh", vec!["-c".into(), "echo abcd".into()]);
+        assert_eq!(solver.solve(&large)?, "abcd");
+        let solver = CommandSolver::new(
+            "sh",
+            vec!["-c".into(), "head -c 1 > /dev/null; echo abcd".into()],
+        );
+        assert_eq!(solver.solve(&large)?, "abcd");
+
+        let solver = CommandSolver::new("sh", vec!["-c".into(), "exit 1".into()]);
+        assert!(solver.solve(&image).is_err());
+
+        Ok(())
+    }
+}
@@ -6,23 +6,17 @@ use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyIn
 use aes::Aes128;
 
 use reqwest::blocking::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use super::{check_response, url, APP_VER_NAME};
+use super::captcha::{CaptchaSolver, StdinSolver};
+use super::{check_response, url, url::Endpoints, Envelope, APP_VER_NAME};
 use crate::error::Error;
-use crate::utils::{md5, pkcs7_padding};
+use crate::utils::{encrypt_password, md5, pkcs7_padding};
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BasicResponse<T> {
-    pub status_code: i32,
-    pub success: bool,
-    pub message: String,
-    pub data: Option<T>,
-}
+pub type BasicResponse<T> = Envelope<T>;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SecurityTokenResponse {
     pub level: u8,
@@ -30,7 +24,9 @@ pub struct SecurityTokenResponse {
 }
 
 /// Login response data definition
-#[derive(Debug, Deserialize)]
+///
+/// Saved as the app login profile, see [`LoginResponse::save`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LoginResponse {
     pub id: String,    // UID
@@ -71,19 +67,141 @@ pub struct LoginResponse {
     pub ext_json: Option<String>,
 }
 
+impl LoginResponse {
+    /// Load the saved profile
+    ///
+    /// Return `None` if the file does not exist
+    pub fn load(path: &str) -> Result<Option<Self>, Error> {
+        load_json(path)
+    }
+
+    /// Save the profile, readable by the owner only
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        crate::utils::file_write(path, &serde_json::to_string(self)?)
+    }
+}
+
+/// Image format detected by magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+    Webp,
+    Unknown,
+}
+
+impl ImageFormat {
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            [0x89, b'P', b'N', b'G', ..] => Self::Png,
+            [0xff, 0xd8, 0xff, ..] => Self::Jpeg,
+            [b'G', b'I', b'F', b'8', ..] => Self::Gif,
+            [b'B', b'M', ..] => Self::Bmp,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Self::Webp,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// File extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+            Self::Bmp => "bmp",
+            Self::Webp => "webp",
+            Self::Unknown => "bin",
+        }
+    }
+}
+
+/// Decoded image captcha
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptchaImage {
+    pub bytes: Vec<u8>,
+    pub format: ImageFormat,
+}
+
+impl CaptchaImage {
+    /// Decode base64 string, with or without the `data:image/...;base64,` prefix
+    pub fn decode(raw: &str) -> Result<Self, Error> {
+        let data = match raw.split_once(";base64,") {
+            Some((_, v)) => v,
+            None => raw,
+        };
+        let bytes = base64::decode(data.trim())?;
+
+        Ok(Self {
+            format: ImageFormat::detect(&bytes),
+            bytes,
+        })
+    }
+
+    /// Write into a temp file, return the path
+    pub fn save_temp(&self) -> Result<std::path::PathBuf, Error> {
+        let path = std::env::temp_dir().join(format!(
+            "yxy-captcha-{}.{}",
+            uuid::Uuid::new_v4().simple(),
+            self.format.extension()
+        ));
+        std::fs::write(&path, &self.bytes)?;
+
+        Ok(path)
+    }
+}
+
+/// Pending login between sending and entering the verification code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginState {
+    pub phone_num: String,
+    pub device_id: String,
+    pub security_token: String,
+    /// Image captcha required if not 0
+    pub level: u8,
+}
+
+impl LoginState {
+    /// Return `None` if the file does not exist
+    pub fn load(path: &str) -> Result<Option<Self>, Error> {
+        load_json(path)
+    }
+
+    /// Save the state, readable by the owner only
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        crate::utils::file_write(path, &serde_json::to_string(self)?)
+    }
+}
+
+fn load_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<Option<T>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(v) => Ok(Some(serde_json::from_str(&v)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub struct LoginHandler {
     pub phone_num: String,
     pub device_id: String,
+    /// App login token of the saved profile
+    pub token: Option<String>,
     client: Client,
+    endpoints: Endpoints,
+    /// Defaults to [`StdinSolver`], prompting on the process stdout and stdin
+    solver: Box<dyn CaptchaSolver>,
 }
 
 mod error_messages {
-    pub const WRONG_VERIFY_CODE: &'static str = "您已输错";
-    pub const BAD_PHONE_NUM: &'static str = "请输入正确的手机号";
-    pub const BAD_PHONE_NUM_FORMAT: &'static str = "手机号码格式错误";
-    pub const TOO_FREQUENT: &'static str = "经过你的";
-    pub const TOO_MANY_TRIES: &'static str = "发送超限，请明天再来";
-    pub const FLOW_CONTROL: &'static str = "触发号码天级流控";
+    pub const WRONG_VERIFY_CODE: &str = "您已输错";
+    pub const BAD_PHONE_NUM: &str = "请输入正确的手机号";
+    pub const BAD_PHONE_NUM_FORMAT: &str = "手机号码格式错误";
+    pub const TOO_FREQUENT: &str = "经过你的";
+    pub const TOO_MANY_TRIES: &str = "发送超限，请明天再来";
+    pub const FLOW_CONTROL: &str = "触发号码天级流控";
+    pub const TOKEN_REVOKED: &str = "登录已失效";
+    pub const WRONG_PASSWORD: &str = "账号或密码错误";
 }
 
 impl LoginHandler {
@@ -94,76 +212,118 @@ impl LoginHandler {
             phone_num,
             client: init_app_sim_client(&device_id)?,
             device_id,
+            token: None,
+            endpoints: Endpoints::default(),
+            solver: Box::new(StdinSolver),
         })
     }
 
     pub fn init(phone_num: &str, device_id: &str) -> Result<Self, Error> {
+        Self::init_with_endpoints(phone_num, device_id, Endpoints::default())
+    }
+
+    /// Init handler requesting the given endpoints
+    pub fn init_with_endpoints(
+        phone_num: &str,
+        device_id: &str,
+        endpoints: Endpoints,
+    ) -> Result<Self, Error> {
         Ok(Self {
             phone_num: phone_num.to_string(),
             device_id: device_id.to_string(),
-            client: init_app_sim_client(&device_id)?,
+            token: None,
+            client: init_app_sim_client(device_id)?,
+            endpoints,
+            solver: Box::new(StdinSolver),
         })
     }
 
+    /// Init handler by the saved profile, reusing its device id and app token
+    pub fn from_profile(profile: &LoginResponse, endpoints: Endpoints) -> Result<Self, Error> {
+        let mut handler =
+            Self::init_with_endpoints(&profile.mobile_phone, &profile.device_id, endpoints)?;
+        handler.token = Some(profile.token.clone());
+
+        Ok(handler)
+    }
+
+    /// Set the image captcha solver
+    pub fn with_captcha_solver<S: CaptchaSolver + 'static>(mut self, solver: S) -> Self {
+        self.solver = Box::new(solver);
+        self
+    }
+
+    /// Init handler by the pending login, to resume it
+    pub fn from_state(state: &LoginState, endpoints: Endpoints) -> Result<Self, Error> {
+        Self::init_with_endpoints(&state.phone_num, &state.device_id, endpoints)
+    }
+
+    /// Pending login of the security token, see [`LoginState`]
+    pub fn login_state(&self, security_token: &SecurityTokenResponse) -> LoginState {
+        LoginState {
+            phone_num: self.phone_num.clone(),
+            device_id: self.device_id.clone(),
+            security_token: security_token.security_token.clone(),
+            level: security_token.level,
+        }
+    }
+
     /// Init general request body
     pub fn get_basic_request_body(&self) -> HashMap<&str, serde_json::Value> {
-        let mut result = HashMap::new();
-        result.insert("appVersion", json!(super::APP_VER));
-        result.insert("deviceId", json!(self.device_id));
-        result.insert("platform", json!("YUNMA_APP"));
-        result.insert("testAccount", json!(1u8));
-
-        result
+        basic_request_body(&self.device_id)
     }
 
     /// Return security token & level
     pub fn get_security_token(&self) -> Result<SecurityTokenResponse, Error> {
-        let mut body = self.get_basic_request_body();
-        body.insert("sceneCode", json!("app_user_login"));
         let mut resp = self
             .client
-            .post(url::app::GET_SECURITY_TOKEN)
-            .json(&body)
+            .post(self.endpoints.app_url(url::app::GET_SECURITY_TOKEN))
+            .json(&security_token_body(&self.device_id))
             .send()?;
         check_response(&mut resp)?;
 
-        let resp_ser: BasicResponse<SecurityTokenResponse> = resp.json()?;
-        if resp_ser.success == false {
-            return Err(Error::Runtime(format!(
-                "Get security token failed: {}",
-                resp_ser.message
-            )));
-        }
-
-        match resp_ser.data {
-            Some(v) => Ok(v),
-            None => Err(Error::EmptyResp),
-        }
+        BasicResponse::decode(url::app::GET_SECURITY_TOKEN, &resp.text()?)?.data()
     }
 
     /// Get image captcha
     /// ------------
     /// Return image captcha base64 string
     pub fn get_captcha_image(&self, security_token: &str) -> Result<String, Error> {
-        let mut body = self.get_basic_request_body();
-        body.insert("securityToken", json!(security_token));
-
         let mut resp = self
             .client
-            .post(url::app::GET_IMAGE_CAPTCHA)
-            .json(&body)
+            .post(self.endpoints.app_url(url::app::GET_IMAGE_CAPTCHA))
+            .json(&captcha_image_body(&self.device_id, security_token))
             .send()?;
         check_response(&mut resp)?;
 
-        let resp_ser: BasicResponse<String> = resp.json()?;
-        if resp_ser.success == false {
-            Err(Error::Runtime(format!(
-                "Get image captcha failed: {}",
-                resp_ser.message
-            )))
+        BasicResponse::decode(url::app::GET_IMAGE_CAPTCHA, &resp.text()?)?.data()
+    }
+
+    /// Get image captcha, decoded
+    pub fn get_captcha(&self, security_token: &str) -> Result<CaptchaImage, Error> {
+        CaptchaImage::decode(&self.get_captcha_image(security_token)?)
+    }
+
+    /// Answer the image captcha of security token by the solver
+    pub fn solve_captcha(&self, security_token: &str) -> Result<String, Error> {
+        self.solver.solve(&self.get_captcha(security_token)?)
+    }
+
+    /// Request to send login verification code sms,
+    /// solve the image captcha if required
+    ///
+    /// Set a solver by [`Self::with_captcha_solver`] unless running in a terminal.
+    ///
+    /// Return whether the user exists
+    pub fn request_verification_code(&self) -> Result<bool, Error> {
+        let security_token = self.get_security_token()?;
+        let captcha = if security_token.level != 0 {
+            Some(self.solve_captcha(&security_token.security_token)?)
         } else {
-            Ok(resp_ser.data.unwrap())
-        }
+            None
+        };
+
+        self.send_verification_code(&security_token.security_token, captcha.as_deref())
     }
 
     /// Request to send login verification code sms
@@ -172,110 +332,243 @@ impl LoginHandler {
         security_token: &str,
         captcha: Option<&str>,
     ) -> Result<bool, Error> {
-        let mut body = self.get_basic_request_body();
-        let app_security_token = get_app_security_token(security_token, &self.device_id)?;
-        body.insert("appSecurityToken", json!(app_security_token));
-        body.insert("securityToken", json!(security_token));
-        body.insert("sendCount", json!(1u8));
-        body.insert("mobilePhone", json!(self.phone_num));
-
-        // If image captcha required
-        if let Some(v) = captcha {
-            body.insert("imageCafptchaValue", json!(v));
-        }
+        let body =
+            verification_code_body(&self.device_id, &self.phone_num, security_token, captcha)?;
 
         let mut resp = self
             .client
-            .post(url::app::SEND_VERIFICATION_CODE)
+            .post(self.endpoints.app_url(url::app::SEND_VERIFICATION_CODE))
             .json(&body)
             .send()?;
         check_response(&mut resp)?;
 
-        /// Define data object
-        #[derive(Debug, Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        struct Data {
-            user_exists: bool,
-        }
+        take_verification_result(&resp.text()?)
+    }
 
-        let resp_ser: BasicResponse<Data> = resp.json()?;
-        if resp_ser.success == false {
-            if resp_ser.status_code == 203 {
-                if resp_ser.message == error_messages::BAD_PHONE_NUM
-                    || resp_ser.message == error_messages::BAD_PHONE_NUM_FORMAT
-                {
-                    return Err(Error::BadPhoneNumber);
-                }
-                if resp_ser.message.starts_with(error_messages::TOO_FREQUENT)
-                    || resp_ser.message == error_messages::FLOW_CONTROL
-                    || resp_ser.message == error_messages::TOO_MANY_TRIES
-                {
-                    return Err(Error::VerificationLimit);
-                }
-            }
+    /// Do login with verification code
+    pub fn do_login(&self, code: &str) -> Result<LoginResponse, Error> {
+        let mut resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_CODE))
+            .json(&login_body(&self.device_id, &self.phone_num, code))
+            .send()?;
+        check_response(&mut resp)?;
 
-            return Err(Error::Runtime(format!(
-                "Send verification code error: {{code: {}, message: {}}}",
-                resp_ser.status_code, resp_ser.message
-            )));
-        }
+        let mut buf = String::new();
+        resp.read_to_string(&mut buf)?;
 
-        // User status
-        let user_exists = resp_ser.data.unwrap().user_exists;
+        parse_login(url::app::DO_LOGIN_BY_CODE, &buf)
+    }
 
-        Ok(user_exists)
+    /// Return Base64 public key of password encryption
+    pub fn get_public_key(&self) -> Result<String, Error> {
+        let mut resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::GET_PUBLIC_KEY))
+            .json(&basic_request_body(&self.device_id))
+            .send()?;
+        check_response(&mut resp)?;
+
+        BasicResponse::decode(url::app::GET_PUBLIC_KEY, &resp.text()?)?.data()
     }
 
-    /// Do login with verification code
-    pub fn do_login(&self, code: &str) -> Result<LoginResponse, Error> {
-        let mut body = self.get_basic_request_body();
-        body.insert("appPlatform", json!("Android"));
-        body.insert("clientId", json!("65l01gpo3p8v6rk"));
-        body.insert("mobilePhone", json!(self.phone_num));
-        body.insert("oaid", json!(""));
-        body.insert("osType", json!("Android"));
-        body.insert("osUuid", json!(self.device_id));
-        body.insert("osVersion", json!(11u8));
-        body.insert("verificationCode", json!(code));
+    /// Do login with password, instead of verification code
+    pub fn do_login_by_password(&self, password: &str) -> Result<LoginResponse, Error> {
+        let public_key = self.get_public_key()?;
+        let body = password_login_body(&self.device_id, &self.phone_num, password, &public_key)?;
 
         let mut resp = self
             .client
-            .post(url::app::DO_LOGIN_BY_CODE)
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_PASSWORD))
             .json(&body)
             .send()?;
         check_response(&mut resp)?;
 
         let mut buf = String::new();
         resp.read_to_string(&mut buf)?;
-        // println!("resp: {}", buf);
-
-        let resp_ser: BasicResponse<LoginResponse> = match serde_json::from_str(&buf) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(Error::Runtime(format!(
-                    "Parsing login response failed: {}\nData: {}",
-                    e, buf
-                )))
-            }
-        };
 
-        if resp_ser.success == false {
-            if resp_ser
-                .message
-                .starts_with(error_messages::WRONG_VERIFY_CODE)
+        parse_login(url::app::DO_LOGIN_BY_PASSWORD, &buf)
+    }
+
+    /// Silent login by the app login token of saved profile
+    /// ------------
+    /// Return [`Error::TokenRevoked`] if the token is no longer valid
+    pub fn do_silent_login(&self) -> Result<LoginResponse, Error> {
+        let body = silent_login_body(&self.device_id, &self.phone_num, self.token.as_deref())?;
+
+        let mut resp = self
+            .client
+            .post(self.endpoints.app_url(url::app::DO_LOGIN_BY_SILENT))
+            .json(&body)
+            .send()?;
+        check_response(&mut resp)?;
+
+        let mut buf = String::new();
+        resp.read_to_string(&mut buf)?;
+
+        parse_login(url::app::DO_LOGIN_BY_SILENT, &buf)
+    }
+}
+
+/// Init general request body of device
+pub(crate) fn basic_request_body(device_id: &str) -> HashMap<&'static str, serde_json::Value> {
+    let mut result = HashMap::new();
+    result.insert("appVersion", json!(super::APP_VER));
+    result.insert("deviceId", json!(device_id));
+    result.insert("platform", json!("YUNMA_APP"));
+    result.insert("testAccount", json!(1u8));
+
+    result
+}
+
+/// Request body of security token
+pub(crate) fn security_token_body(device_id: &str) -> HashMap<&'static str, serde_json::Value> {
+    let mut body = basic_request_body(device_id);
+    body.insert("sceneCode", json!("app_user_login"));
+
+    body
+}
+
+/// Request body of image captcha
+pub(crate) fn captcha_image_body(
+    device_id: &str,
+    security_token: &str,
+) -> HashMap<&'static str, serde_json::Value> {
+    let mut body = basic_request_body(device_id);
+    body.insert("securityToken", json!(security_token));
+
+    body
+}
+
+/// Request body of sending verification code
+pub(crate) fn verification_code_body(
+    device_id: &str,
+    phone_num: &str,
+    security_token: &str,
+    captcha: Option<&str>,
+) -> Result<HashMap<&'static str, serde_json::Value>, Error> {
+    let mut body = basic_request_body(device_id);
+    let app_security_token = get_app_security_token(security_token, device_id)?;
+    body.insert("appSecurityToken", json!(app_security_token));
+    body.insert("securityToken", json!(security_token));
+    body.insert("sendCount", json!(1u8));
+    body.insert("mobilePhone", json!(phone_num));
+
+    // If image captcha required
+    if let Some(v) = captcha {
+        body.insert("imageCafptchaValue", json!(v));
+    }
+
+    Ok(body)
+}
+
+/// Request body of login by verification code
+pub(crate) fn login_body(
+    device_id: &str,
+    phone_num: &str,
+    code: &str,
+) -> HashMap<&'static str, serde_json::Value> {
+    let mut body = basic_request_body(device_id);
+    body.insert("appPlatform", json!("Android"));
+    body.insert("clientId", json!("65l01gpo3p8v6rk"));
+    body.insert("mobilePhone", json!(phone_num));
+    body.insert("oaid", json!(""));
+    body.insert("osType", json!("Android"));
+    body.insert("osUuid", json!(device_id));
+    body.insert("osVersion", json!(11u8));
+    body.insert("verificationCode", json!(code));
+
+    body
+}
+
+/// Request body of login by password, encrypted by the public key
+pub(crate) fn password_login_body(
+    device_id: &str,
+    phone_num: &str,
+    password: &str,
+    public_key: &str,
+) -> Result<HashMap<&'static str, serde_json::Value>, Error> {
+    let mut body = basic_request_body(device_id);
+    body.insert("appPlatform", json!("Android"));
+    body.insert("clientId", json!("65l01gpo3p8v6rk"));
+    body.insert("mobilePhone", json!(phone_num));
+    body.insert("osType", json!("Android"));
+    body.insert("osUuid", json!(device_id));
+    body.insert("osVersion", json!(11u8));
+    body.insert("password", json!(encrypt_password(password, public_key)?));
+
+    Ok(body)
+}
+
+/// Request body of silent login by app token
+pub(crate) fn silent_login_body(
+    device_id: &str,
+    phone_num: &str,
+    token: Option<&str>,
+) -> Result<HashMap<&'static str, serde_json::Value>, Error> {
+    let token = match token {
+        Some(v) => v,
+        None => return Err(Error::Runtime("No app login token".into())),
+    };
+    let mut body = basic_request_body(device_id);
+    body.insert("appPlatform", json!("Android"));
+    body.insert("mobilePhone", json!(phone_num));
+    body.insert("osType", json!("Android"));
+    body.insert("osUuid", json!(device_id));
+    body.insert("token", json!(token));
+
+    Ok(body)
+}
+
+/// Define data object of sending verification code
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct VerificationCodeData {
+    user_exists: bool,
+}
+
+/// Return whether the user exists
+pub(crate) fn take_verification_result(body: &str) -> Result<bool, Error> {
+    let resp = BasicResponse::<VerificationCodeData>::decode_with(
+        url::app::SEND_VERIFICATION_CODE,
+        body,
+        |resp| {
+            if resp.status_code != 203 {
+                return None;
+            }
+            if resp.message == error_messages::BAD_PHONE_NUM
+                || resp.message == error_messages::BAD_PHONE_NUM_FORMAT
+            {
+                return Some(Error::BadPhoneNumber);
+            }
+            if resp.message.starts_with(error_messages::TOO_FREQUENT)
+                || resp.message == error_messages::FLOW_CONTROL
+                || resp.message == error_messages::TOO_MANY_TRIES
             {
-                return Err(Error::BadVerificationCode);
+                return Some(Error::VerificationLimit);
             }
+            None
+        },
+    )?;
 
-            return Err(Error::Runtime(format!(
-                "Login error: {{code: {}, msg: {}}}",
-                resp_ser.status_code, resp_ser.message
-            )));
-        }
-        let result = resp_ser.data.unwrap();
+    // User status
+    Ok(resp.data()?.user_exists)
+}
 
-        Ok(result)
-    }
+/// Parse login response text of the endpoint
+pub(crate) fn parse_login(endpoint: &str, buf: &str) -> Result<LoginResponse, Error> {
+    BasicResponse::<LoginResponse>::decode_with(endpoint, buf, |resp| {
+        if resp.message.starts_with(error_messages::WRONG_VERIFY_CODE) {
+            Some(Error::BadVerificationCode)
+        } else if resp.message.starts_with(error_messages::WRONG_PASSWORD) {
+            Some(Error::BadPassword)
+        } else if resp.message.starts_with(error_messages::TOKEN_REVOKED) {
+            Some(Error::TokenRevoked)
+        } else {
+            None
+        }
+    })?
+    .data()
 }
 
 /// Random device id generator
@@ -290,38 +583,61 @@ pub fn gen_device_id() -> String {
 /// Init App simulated client
 pub fn init_app_sim_client(device_id: &str) -> Result<reqwest::blocking::Client, Error> {
     let builder: reqwest::blocking::ClientBuilder = reqwest::blocking::Client::builder();
+    let result: reqwest::blocking::Client = builder
+        .connect_timeout(std::time::Duration::new(5, 0))
+        .user_agent(app_sim_user_agent(device_id))
+        .default_headers(app_sim_headers())
+        .build()?;
+
+    Ok(result)
+}
+
+/// Define App simulated headers
+pub(crate) fn app_sim_headers() -> reqwest::header::HeaderMap {
     let mut headers = super::get_default_headers();
     headers.insert(
         "Domain-Name",
         reqwest::header::HeaderValue::from_static("campus"),
     );
-    let result: reqwest::blocking::Client = builder
-        .connect_timeout(std::time::Duration::new(5, 0))
-        .user_agent(format!("{}{}", super::USER_AGENT, device_id))
-        .default_headers(headers)
-        .build()?;
 
-    Ok(result)
+    headers
+}
+
+/// App simulated user agent of device
+pub(crate) fn app_sim_user_agent(device_id: &str) -> String {
+    format!("{}{}", super::USER_AGENT, device_id)
 }
 
 /// Encrypt device id
 pub fn get_app_security_token(security_token: &str, device_id: &str) -> Result<String, Error> {
-    let key = GenericArray::clone_from_slice(security_token[..16].as_bytes());
+    let bad_token = || Error::Runtime(format!("Bad security token: {}", security_token));
+
+    let raw = security_token.as_bytes();
+    if raw.len() <= 32 {
+        return Err(bad_token());
+    }
+    let key = GenericArray::clone_from_slice(&raw[..16]);
     let cipher = Aes128::new(&key);
 
-    let text = base64::decode(security_token[32..].as_bytes())?;
+    let text = base64::decode(&raw[32..])?;
+    if text.len() % 16 != 0 {
+        return Err(bad_token());
+    }
 
-    let mut blocks = Vec::new();
-    (0..text.len()).step_by(16).for_each(|x| {
-        blocks.push(GenericArray::clone_from_slice(text[x..x + 16].as_ref()));
-    });
+    let mut blocks: Vec<_> = text
+        .chunks(16)
+        .map(GenericArray::clone_from_slice)
+        .collect();
 
     cipher.decrypt_blocks(&mut blocks);
 
-    let t: Vec<u8> = blocks.iter().flatten().map(|&x| x as u8).collect();
+    let t: Vec<u8> = blocks.iter().flatten().copied().collect();
 
-    let last = t.last().unwrap().clone();
-    let index: usize = t.len() - usize::from(last);
+    // Strip PKCS#7 padding
+    let index = match t.last() {
+        Some(&last) if last != 0 && usize::from(last) <= t.len() => t.len() - usize::from(last),
+        _ => return Err(bad_token()),
+    };
     let t_final: String = t[..index].iter().map(|&x| x as char).collect();
 
     let time_stamp = chrono::prelude::Local::now().timestamp();
@@ -344,13 +660,13 @@ pub fn get_app_security_token(security_token: &str, device_id: &str) -> Result<S
     let mut blocks_2 = Vec::new();
     (0..padded_text.len()).step_by(16).for_each(|x| {
         blocks_2.push(GenericArray::clone_from_slice(
-            padded_text[x..x + 16].as_ref(),
+            &padded_text.as_bytes()[x..x + 16],
         ));
     });
 
     cipher.encrypt_blocks(&mut blocks_2);
 
-    let encrypted_text: Vec<u8> = blocks_2.iter().flatten().map(|&x| x as u8).collect();
+    let encrypted_text: Vec<u8> = blocks_2.iter().flatten().copied().collect();
 
     let stage_4 = base64::encode(encrypted_text);
 
@@ -370,6 +686,27 @@ mod test {
         )?;
         assert_eq!("RxTdUD90Eg91tGZHyhTKwjX9v3fH8WWGgQ3vQ5CuiC", &result[..42]);
 
+        // Malformed
+        assert!(get_app_security_token("short", "12345678").is_err());
+        assert!(get_app_security_token(
+            "ce295733862b93cb376efef661c21b4dEW6CpH8wFHp/RvViKZiJ",
+            "12345678"
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn captcha_image() -> Result<(), Error> {
+        let image = CaptchaImage::decode("data:image/png;base64,iVBORw0KGgo=")?;
+        assert_eq!(image.format, ImageFormat::Png);
+        assert_eq!(image.bytes[..4], [0x89, b'P', b'N', b'G']);
+
+        assert_eq!(CaptchaImage::decode("/9j/4A==")?.format, ImageFormat::Jpeg);
+        assert_eq!(ImageFormat::detect(b"GIF89a"), ImageFormat::Gif);
+        assert_eq!(ImageFormat::detect(b"?"), ImageFormat::Unknown);
+
         Ok(())
     }
 }
@@ -2,18 +2,23 @@
 use std::{io::Read, sync::Arc, time::Duration};
 
 use reqwest::{blocking::Response, cookie::Jar, header};
+use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::error::Error;
+use url::Endpoints;
 
 pub mod app;
+#[cfg(feature = "async")]
+pub mod r#async;
 pub mod auth;
+pub mod captcha;
 pub mod login;
 pub mod notice;
 pub mod url;
 
-const APP_VER: &'static str = "410";
-const APP_VER_NAME: &'static str = "4.1.0";
-const USER_AGENT: &'static str = const_format::formatcp!(
+const APP_VER: &str = "410";
+const APP_VER_NAME: &str = "4.1.0";
+const USER_AGENT: &str = const_format::formatcp!(
     "\
 Mozilla/5.0 (Linux; Android 11; Android for arm64; wv) \
 AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 \
@@ -35,6 +40,18 @@ fn get_default_headers() -> header::HeaderMap {
     headers
 }
 
+/// Define cookie jar carrying the session id.
+fn session_jar(session: &str, endpoints: &Endpoints) -> Result<Arc<Jar>, Error> {
+    let base = match reqwest::Url::parse(&endpoints.application) {
+        Ok(v) => v,
+        Err(e) => return Err(Error::Runtime(format!("Bad application url: {}", e))),
+    };
+    let jar = Jar::default();
+    jar.add_cookie_str(&format!("{}={}", auth::SESSION_KEY, session), &base);
+
+    Ok(Arc::new(jar))
+}
+
 /// Init default reqwest (blocking) client.
 pub fn init_default_client() -> Result<reqwest::blocking::Client, Error> {
     let builder: reqwest::blocking::ClientBuilder = reqwest::blocking::Client::builder();
@@ -51,24 +68,24 @@ pub fn init_default_client() -> Result<reqwest::blocking::Client, Error> {
 #[derive(Debug)]
 pub struct Handler {
     client: reqwest::blocking::Client,
+    endpoints: Endpoints,
 }
 
 impl Handler {
     pub fn new(session: &str) -> Result<Self, Error> {
+        Self::with_endpoints(session, Endpoints::default())
+    }
+
+    /// Init handler requesting the given endpoints
+    pub fn with_endpoints(session: &str, endpoints: Endpoints) -> Result<Self, Error> {
         Ok(Self {
-            client: {
-                let jar = Jar::default();
-                jar.add_cookie_str(
-                    &format!("{}={}", auth::SESSION_KEY, session),
-                    &reqwest::Url::parse(url::application::BASE_URL).unwrap(),
-                );
-                reqwest::blocking::Client::builder()
-                    .connect_timeout(Duration::new(5, 0))
-                    .user_agent(USER_AGENT)
-                    .default_headers(get_default_headers())
-                    .cookie_provider(Arc::new(jar))
-                    .build()?
-            },
+            client: reqwest::blocking::Client::builder()
+                .connect_timeout(Duration::new(5, 0))
+                .user_agent(USER_AGENT)
+                .default_headers(get_default_headers())
+                .cookie_provider(session_jar(session, &endpoints)?)
+                .build()?,
+            endpoints,
         })
     }
 }
@@ -77,12 +94,120 @@ fn check_response(res: &mut Response) -> Result<(), Error> {
     if !res.status().is_success() {
         let mut text = String::new();
         res.read_to_string(&mut text)?;
-        return Err(Error::Runtime(format!(
-            "Bad response: {}\nText: {}",
-            res.status(),
-            text,
-        )));
+        return Err(bad_response(res.url().path(), res.status(), &text));
     }
 
     Ok(())
 }
+
+/// Build the error of non-success HTTP status
+fn bad_response(endpoint: &str, status: reqwest::StatusCode, text: &str) -> Error {
+    Error::api(
+        endpoint,
+        status.as_u16().into(),
+        format!("Bad response: {}", status),
+        text,
+    )
+}
+
+/// Response envelope shared by the platform APIs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    #[serde(default)]
+    pub status_code: i32,
+    pub success: bool,
+    #[serde(default)]
+    pub message: String,
+    /// `rows` of list queries
+    #[serde(alias = "rows")]
+    pub data: Option<T>,
+}
+
+impl<T: DeserializeOwned> Envelope<T> {
+    /// Decode the response body of the endpoint
+    ///
+    /// Failures are mapped to [`Error::Api`]
+    pub(crate) fn decode(endpoint: &str, body: &str) -> Result<Self, Error> {
+        Self::decode_with(endpoint, body, |_| None)
+    }
+
+    /// Decode the response body of the endpoint authorized by session
+    ///
+    /// Failures are mapped to [`Error::AuthExpired`] on status code 204,
+    /// otherwise [`Error::Api`]
+    pub(crate) fn decode_session(endpoint: &str, body: &str) -> Result<Self, Error> {
+        Self::decode_with(endpoint, body, |resp| {
+            (resp.status_code == 204).then_some(Error::AuthExpired)
+        })
+    }
+
+    /// Decode the response body of the endpoint,
+    /// mapping failures by `f` first, see [`Envelope::decode`]
+    pub(crate) fn decode_with<F>(endpoint: &str, body: &str, f: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&Self) -> Option<Error>,
+    {
+        let resp: Self = serde_json::from_str(body)?;
+        if resp.success {
+            return Ok(resp);
+        }
+
+        if let Some(e) = f(&resp) {
+            return Err(e);
+        }
+        Err(Error::api(endpoint, resp.status_code, resp.message, body))
+    }
+
+    /// Take the data, [`Error::EmptyResp`] if absent
+    pub fn data(self) -> Result<T, Error> {
+        self.data.ok_or(Error::EmptyResp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn envelope() -> Result<(), Error> {
+        let body = r#"{"statusCode":0,"success":true,"total":1,"rows":[1]}"#;
+        assert_eq!(Envelope::<Vec<u8>>::decode("/rows", body)?.data()?, [1]);
+
+        let body = r#"{"statusCode":204,"success":false,"message":"expired"}"#;
+        assert!(matches!(
+            Envelope::<u8>::decode_session("/expired", body),
+            Err(Error::AuthExpired)
+        ));
+        assert!(matches!(
+            Envelope::<u8>::decode("/expired", body),
+            Err(Error::Api {
+                status_code: 204,
+                ..
+            })
+        ));
+
+        // Message absent
+        let body = r#"{"statusCode":500,"success":false}"#;
+        match Envelope::<u8>::decode("/fail", body) {
+            Err(Error::Api {
+                endpoint,
+                status_code: 500,
+                raw_body,
+                ..
+            }) => {
+                assert_eq!(endpoint, "/fail");
+                assert_eq!(raw_body, body);
+            }
+            v => panic!("{:?}", v),
+        }
+
+        let body = r#"{"statusCode":0,"success":true}"#;
+        assert!(matches!(
+            Envelope::<u8>::decode("/empty", body)?.data(),
+            Err(Error::EmptyResp)
+        ));
+
+        Ok(())
+    }
+}
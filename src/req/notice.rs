@@ -2,41 +2,73 @@
 use reqwest::blocking::Client;
 use serde::Deserialize;
 
-use super::url;
+use super::url::Endpoints;
 use crate::error::Error;
 
-pub fn push_message(key: &str, title: &str, desp: &str) -> Result<(String, String), Error> {
+pub fn push_message(
+    endpoints: &Endpoints,
+    key: &str,
+    title: &str,
+    desp: &str,
+) -> Result<(String, String), Error> {
     let client = Client::new();
     let resp = client
-        .post(&format!("{}{}.send", url::SERVER_CHAN, key))
-        .form(&vec![("title", title), ("desp", desp)])
+        .post(endpoints.server_chan_url(key))
+        .form(&[("title", title), ("desp", desp)])
         .send()?;
 
-    #[derive(Debug, Deserialize)]
-    struct Response {
-        code: i32,
-        message: String,
-        data: Option<Data>,
-    }
+    take_push_result(&resp.text()?)
+}
 
-    #[derive(Debug, Deserialize)]
-    struct Data {
-        pushid: String,
-        readkey: String,
-        // error: String,
-        // errno: i32,
-    }
+#[derive(Debug, Deserialize)]
+pub(crate) struct PushResponse {
+    code: i32,
+    message: String,
+    data: Option<PushData>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PushData {
+    pushid: String,
+    readkey: String,
+    // error: String,
+    // errno: i32,
+}
 
-    let resp_ser: Response = resp.json()?;
-    if resp_ser.code != 0 {
-        return Err(Error::Runtime(format!(
-            "ServerChan message push failed: {}",
-            resp_ser.message
-        )));
+/// Return (push id, read key)
+pub(crate) fn take_push_result(body: &str) -> Result<(String, String), Error> {
+    let resp: PushResponse = serde_json::from_str(body)?;
+    if resp.code != 0 {
+        return Err(Error::api(
+            super::url::SERVER_CHAN_SEND,
+            resp.code,
+            resp.message,
+            body,
+        ));
     }
 
-    match resp_ser.data {
+    match resp.data {
         Some(v) => Ok((v.pushid, v.readkey)),
         None => Err(Error::EmptyResp),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_error() {
+        match take_push_result(r#"{"code":40001,"message":"bad key"}"#) {
+            Err(Error::Api {
+                endpoint,
+                status_code,
+                ..
+            }) => {
+                assert_eq!(endpoint, "/<key>.send");
+                assert_eq!(status_code, 40001);
+            }
+            v => panic!("{:?}", v),
+        }
+    }
+}
@@ -1,42 +1,140 @@
 //! Define public API urls
+//!
+//! Each service has a production base URL and the paths of its APIs.
+//! [`Endpoints`] joins them, so the whole flow can be pointed to other
+//! servers (e.g. staging or a local stand-in server).
+use serde::Deserialize;
 
 pub mod auth {
-    use const_format::concatcp;
+    pub const BASE_URL: &str = "https://auth.xiaofubao.com";
 
-    pub const BASE_URL: &'static str = "https://auth.xiaofubao.com";
-
-    pub const OAUTH_URL: &'static str = concatcp!(BASE_URL, "/authoriz/getCodeV2");
+    pub const OAUTH_URL: &str = "/authoriz/getCodeV2";
 }
 
 pub mod application {
-    use const_format::concatcp;
+    pub const BASE_URL: &str = "https://application.xiaofubao.com";
+
+    pub const GET_USER_FOR_AUTHORIZE: &str = "/app/login/getUser4Authorize";
+
+    pub const QUERY_BIND: &str = "/app/electric/queryBind";
 
-    pub const BASE_URL: &'static str = "https://application.xiaofubao.com";
+    pub const QUERY_ELECTRICITY: &str = "/app/electric/queryISIMSRoomSurplus";
 
-    pub const GET_USER_FOR_AUTHORIZE: &'static str =
-        concatcp!(BASE_URL, "/app/login/getUser4Authorize");
+    pub const QUERY_AREA: &str = "/app/electric/queryISIMSArea";
 
-    pub const QUERY_BIND: &'static str = concatcp!(BASE_URL, "/app/electric/queryBind");
+    pub const QUERY_BUILDING: &str = "/app/electric/queryISIMSBuilding";
 
-    pub const QUERY_ELECTRICITY: &'static str =
-        concatcp!(BASE_URL, "/app/electric/queryISIMSRoomSurplus");
+    pub const QUERY_FLOOR: &str = "/app/electric/queryISIMSFloor";
+
+    pub const QUERY_ROOM: &str = "/app/electric/queryISIMSRoom";
+
+    /// Unverified, the contract is not confirmed by platform source or captured traffic,
+    /// see [`crate::req::Handler::recharge`]
+    pub const RECHARGE: &str = "/app/electric/recharge";
 }
 
 pub mod app {
-    use const_format::concatcp;
+    pub const BASE_URL: &str = "https://compus.xiaofubao.com";
+
+    pub const GET_SECURITY_TOKEN: &str = "/common/security/token";
+
+    pub const SEND_VERIFICATION_CODE: &str = "/compus/user/sendLoginVerificationCode";
+
+    pub const GET_IMAGE_CAPTCHA: &str = "/common/security/imageCaptcha";
+
+    pub const DO_LOGIN_BY_CODE: &str = "/login/doLoginByVerificationCode";
+
+    pub const DO_LOGIN_BY_SILENT: &str = "/login/doLoginBySilent";
+
+    pub const GET_PUBLIC_KEY: &str = "/login/getPublicKey";
+
+    pub const DO_LOGIN_BY_PASSWORD: &str = "/login/doLoginByPwd";
+}
+
+pub const SERVER_CHAN: &str = "https://sctapi.ftqq.com/";
+
+/// Path of ServerChan push API, `<key>` is the send key, kept out of errors
+pub const SERVER_CHAN_SEND: &str = "/<key>.send";
+
+/// Base URLs of services
+///
+/// Defaults to the production platform.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Endpoints {
+    pub auth: String,
+    pub application: String,
+    pub app: String,
+    pub server_chan: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            auth: auth::BASE_URL.into(),
+            application: application::BASE_URL.into(),
+            app: app::BASE_URL.into(),
+            server_chan: SERVER_CHAN.into(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Point all services to the same base URL
+    pub fn local(base: &str) -> Self {
+        Self {
+            auth: base.into(),
+            application: base.into(),
+            app: base.into(),
+            server_chan: base.into(),
+        }
+    }
 
-    pub const BASE_URL: &'static str = "https://compus.xiaofubao.com";
+    /// Full URL of auth service API
+    pub fn auth_url(&self, path: &str) -> String {
+        join(&self.auth, path)
+    }
 
-    pub const GET_SECURITY_TOKEN: &'static str = concatcp!(BASE_URL, "/common/security/token");
+    /// Full URL of application service API
+    pub fn application_url(&self, path: &str) -> String {
+        join(&self.application, path)
+    }
 
-    pub const SEND_VERIFICATION_CODE: &'static str =
-        concatcp!(BASE_URL, "/compus/user/sendLoginVerificationCode");
+    /// Full URL of app service API
+    pub fn app_url(&self, path: &str) -> String {
+        join(&self.app, path)
+    }
 
-    pub const GET_IMAGE_CAPTCHA: &'static str =
-        concatcp!(BASE_URL, "/common/security/imageCaptcha");
+    /// Full URL of ServerChan push API
+    pub fn server_chan_url(&self, key: &str) -> String {
+        join(&self.server_chan, &SERVER_CHAN_SEND.replace("<key>", key))
+    }
+}
 
-    pub const DO_LOGIN_BY_CODE: &'static str =
-        concatcp!(BASE_URL, "/login/doLoginByVerificationCode");
+fn join(base: &str, path: &str) -> String {
+    format!("{}{}", base.trim_end_matches('/'), path)
 }
 
-pub const SERVER_CHAN: &'static str = "https://sctapi.ftqq.com/";
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoints_join() {
+        let default = Endpoints::default();
+        assert_eq!(
+            default.application_url(application::QUERY_BIND),
+            "https://application.xiaofubao.com/app/electric/queryBind"
+        );
+        assert_eq!(
+            default.server_chan_url("key"),
+            "https://sctapi.ftqq.com/key.send"
+        );
+
+        let local = Endpoints::local("http://127.0.0.1:8080/");
+        assert_eq!(
+            local.auth_url(auth::OAUTH_URL),
+            "http://127.0.0.1:8080/authoriz/getCodeV2"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Session
+//! ------------
+//! Authorized session of the application service,
+//! with the user info and usage times, cached in JSON.
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::req::{auth::UserInfo, url::Endpoints, Handler};
+
+/// Authorized session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Authorized by
+    pub uid: String,
+    /// Session id, value of [`crate::req::auth::SESSION_KEY`] cookie
+    pub id: String,
+    /// Unix timestamp in seconds, `0` if unknown
+    pub created_at: i64,
+    /// Unix timestamp of the last successful use, `0` if unknown
+    pub last_used: i64,
+    /// `None` if loaded from a legacy cache
+    pub user: Option<UserInfo>,
+}
+
+impl Session {
+    /// Session just authorized
+    pub fn new(uid: &str, id: String, user: UserInfo) -> Self {
+        let now = Local::now().timestamp();
+        Self {
+            uid: uid.into(),
+            id,
+            created_at: now,
+            last_used: now,
+            user: Some(user),
+        }
+    }
+
+    /// Session of a known session id, e.g. cached by other means
+    pub fn from_id(uid: &str, id: &str) -> Self {
+        Self {
+            uid: uid.into(),
+            id: id.into(),
+            created_at: 0,
+            last_used: 0,
+            user: None,
+        }
+    }
+
+    /// Authorize by uid
+    pub fn auth(uid: &str, endpoints: &Endpoints) -> Result<Self, Error> {
+        let (id, user) = crate::auth_with_endpoints(uid, endpoints)?;
+        Ok(Self::new(uid, id, user))
+    }
+
+    /// Reauthorize by the uid, e.g. on [`Error::AuthExpired`]
+    pub fn refresh(&mut self, endpoints: &Endpoints) -> Result<(), Error> {
+        *self = Self::auth(&self.uid, endpoints)?;
+        Ok(())
+    }
+
+    /// Mark as used successfully
+    pub fn touch(&mut self) {
+        self.last_used = Local::now().timestamp();
+    }
+
+    /// Init handler of the session
+    pub fn handler(&self, endpoints: Endpoints) -> Result<Handler, Error> {
+        Handler::with_endpoints(&self.id, endpoints)
+    }
+
+    /// Load the session of uid from cache file
+    ///
+    /// Return `None` if the file does not exist or is cached for another uid.
+    /// The legacy cache holding a bare session id is accepted.
+    pub fn load(path: &str, uid: &str) -> Result<Option<Self>, Error> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        match serde_json::from_str::<Self>(&text) {
+            Ok(v) if v.uid == uid => Ok(Some(v)),
+            Ok(_) => Ok(None),
+            Err(_) if !text.trim().is_empty() && !text.trim_start().starts_with('{') => {
+                Ok(Some(Self::from_id(uid, text.trim())))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save to cache file
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        crate::utils::file_write(path, &serde_json::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("yxy-session-{}", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        assert_eq!(Session::load(path, "uid")?, None);
+
+        // Legacy bare session id
+        std::fs::write(path, "legacy-session")?;
+        let mut session = Session::load(path, "uid")?.unwrap();
+        assert_eq!(session.id, "legacy-session");
+        assert_eq!(session.user, None);
+
+        session.touch();
+        session.save(path)?;
+        assert_eq!(Session::load(path, "uid")?, Some(session));
+        // Cached for another uid
+        assert_eq!(Session::load(path, "other")?, None);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
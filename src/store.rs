@@ -0,0 +1,192 @@
+//! Encrypted store
+//! ------------
+//! Secrets (uid, sessions, app login profile) encrypted by AES-256-GCM
+//! with a key derived from passphrase by PBKDF2-HMAC-SHA256.
+//!
+//! The file is replaced atomically, readable by the owner only, see [`crate::utils::file_write`].
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::req::login::LoginResponse;
+use crate::session::Session;
+
+/// PBKDF2 iterations of new store files
+pub const KDF_ROUNDS: u32 = 100_000;
+
+/// Accepted PBKDF2 iterations of store files, a tampered value could hang the key derivation
+pub const KDF_ROUNDS_RANGE: std::ops::RangeInclusive<u32> = 10_000..=1_000_000;
+
+/// Secrets kept in the store
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Secrets {
+    pub uid: Option<String>,
+    /// Sessions by uid
+    pub sessions: BTreeMap<String, Session>,
+    /// App login profile, with the token and device id
+    pub profile: Option<LoginResponse>,
+}
+
+/// Encrypted file format
+#[derive(Debug, Serialize, Deserialize)]
+struct Sealed {
+    version: u8,
+    rounds: u32,
+    /// Base64 encoded
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+/// Encrypted store file
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Load and decrypt secrets
+    ///
+    /// Return empty secrets if the file does not exist
+    pub fn load(&self, passphrase: &str) -> Result<Secrets, Error> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Secrets::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let plain = open(passphrase, &serde_json::from_str(&text)?)?;
+
+        Ok(serde_json::from_slice(&plain)?)
+    }
+
+    /// Encrypt and save secrets
+    pub fn save(&self, passphrase: &str, secrets: &Secrets) -> Result<(), Error> {
+        let sealed = seal(passphrase, &serde_json::to_vec(secrets)?)?;
+        crate::utils::file_write(
+            &self.path.to_string_lossy(),
+            &serde_json::to_string(&sealed)?,
+        )
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    key
+}
+
+fn seal(passphrase: &str, plain: &[u8]) -> Result<Sealed, Error> {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, KDF_ROUNDS).into());
+    let data = cipher
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|_| Error::Runtime("Fail to encrypt the store".into()))?;
+
+    Ok(Sealed {
+        version: 1,
+        rounds: KDF_ROUNDS,
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        data: base64::encode(data),
+    })
+}
+
+fn open(passphrase: &str, sealed: &Sealed) -> Result<Vec<u8>, Error> {
+    if sealed.version != 1 {
+        return Err(Error::Runtime(format!(
+            "Unsupported store version: {}",
+            sealed.version
+        )));
+    }
+    if !KDF_ROUNDS_RANGE.contains(&sealed.rounds) {
+        return Err(Error::Runtime(format!(
+            "Bad store rounds: {}",
+            sealed.rounds
+        )));
+    }
+    let nonce = base64::decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(Error::Runtime("Bad store nonce".into()));
+    }
+
+    let key = derive_key(passphrase, &base64::decode(&sealed.salt)?, sealed.rounds);
+    Aes256Gcm::new(&key.into())
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            base64::decode(&sealed.data)?.as_ref(),
+        )
+        .map_err(|_| Error::Runtime("Fail to decrypt the store, wrong passphrase?".into()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn store() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("yxy-store-{}", uuid::Uuid::new_v4()));
+        let store = Store::new(&path);
+        assert_eq!(store.load("pass")?, Secrets::default());
+
+        let mut secrets = Secrets {
+            uid: Some("secret-uid".into()),
+            ..Default::default()
+        };
+        secrets
+            .sessions
+            .insert("secret-uid".into(), Session::from_id("secret-uid", "ses"));
+        store.save("pass", &secrets)?;
+
+        let text = std::fs::read_to_string(&path)?;
+        assert!(!text.contains("secret-uid"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert_eq!(store.load("pass")?, secrets);
+        assert!(store.load("wrong").is_err());
+
+        // Replaced, no temp file left
+        store.save("pass", &Secrets::default())?;
+        assert_eq!(store.load("pass")?, Secrets::default());
+        let dir = path.parent().unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(!std::fs::read_dir(dir)?.any(|e| e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(&format!(".{}", name))));
+
+        // Tampered rounds
+        let mut sealed: Sealed = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        sealed.rounds = u32::MAX;
+        std::fs::write(&path, serde_json::to_string(&sealed)?)?;
+        match store.load("pass") {
+            Err(Error::Runtime(e)) => assert_eq!(e, format!("Bad store rounds: {}", u32::MAX)),
+            v => panic!("{:?}", v),
+        }
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
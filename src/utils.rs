@@ -1,15 +1,15 @@
-use base64;
 use rsa::{pkcs8::DecodePublicKey, PaddingScheme, PublicKey, RsaPublicKey};
-use std::io::Write;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
 
 use crate::error::Error;
 
 /// Parse unformatted pure Base64 public key to PEM format
 pub fn parse_public_key_pem(raw: &str) -> String {
-    let bytes = raw.as_bytes();
     let mut result = String::from("-----BEGIN PUBLIC KEY-----\n");
-    for i in 0..bytes.len() {
-        result.push(bytes[i] as char);
+    for (i, &b) in raw.as_bytes().iter().enumerate() {
+        result.push(b as char);
         if (i + 1) % 64 == 0 {
             result.push('\n')
         }
@@ -45,13 +45,76 @@ pub fn encrypt_password(pass: &str, raw_pub: &str) -> Result<String, Error> {
 
 /// Write string to file
 ///
-/// Auto create file
+/// Auto create file, readable & writable by the owner only (on Unix).
+///
+/// Written to a temp file in the same directory, then renamed over the file,
+/// so the old content is kept if interrupted.
 pub fn file_write(path: &str, s: &str) -> Result<(), Error> {
-    let mut f = std::fs::File::create(path)?;
-    f.write(s.as_bytes())?;
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::Runtime(format!("Bad file path: {}", path.display())))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        uuid::Uuid::new_v4()
+    ));
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = options.open(&temp).and_then(|mut f| {
+        f.write_all(s.as_bytes())?;
+        f.sync_all()?;
+        std::fs::rename(&temp, path)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+
+    Ok(result?)
+}
+
+/// Append the value to JSON Lines file
+///
+/// Auto create file
+pub fn append_json_line<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<(), Error> {
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    f.write_all(line.as_bytes())?;
     Ok(())
 }
 
+/// Load all values of JSON Lines file, blank lines skipped
+///
+/// Return empty list if the file does not exist
+pub fn load_json_lines<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> Result<Vec<T>, Error> {
+    let f = match std::fs::File::open(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut result = Vec::new();
+    for line in BufReader::new(f).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        result.push(serde_json::from_str(&line)?);
+    }
+
+    Ok(result)
+}
+
 /// PKCS#7 Padding method
 pub fn pkcs7_padding(message: &str, block_size: usize) -> String {
     let padding_size = block_size - message.len() % block_size;
@@ -0,0 +1,218 @@
+//! Watch mode
+//! ------------
+//! Poll electricity on a schedule, notify on state transitions only.
+use std::time::Duration;
+
+use yxy::error::Error;
+use yxy::req::url::Endpoints;
+use yxy::session::Session;
+
+use crate::conf::{Account, Config};
+
+/// Parse interval like `45s`, `30m`, `1h30m`, `1d`
+///
+/// Plain number is in seconds
+pub fn parse_interval(s: &str) -> Result<Duration, Error> {
+    let bad = || Error::Runtime(format!("Bad interval: {}", s));
+
+    let mut secs: u64 = 0;
+    let mut num = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(bad()),
+        };
+        let n: u64 = num.parse().map_err(|_| bad())?;
+        secs = n
+            .checked_mul(unit)
+            .and_then(|v| secs.checked_add(v))
+            .ok_or_else(bad)?;
+        num.clear();
+    }
+    if !num.is_empty() {
+        let n: u64 = num.parse().map_err(|_| bad())?;
+        secs = secs.checked_add(n).ok_or_else(bad)?;
+    }
+
+    if secs == 0 {
+        return Err(bad());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Electricity state transition
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// Turned into warning state
+    Warning,
+    /// Surplus increased
+    Recharged { from: f32 },
+}
+
+/// Track electricity state between polls
+#[derive(Debug, Default)]
+pub struct Watcher {
+    last_soc: Option<f32>,
+    warning: bool,
+}
+
+impl Watcher {
+    /// Update state by polled surplus, return transitions
+    pub fn update(&mut self, soc: f32, warning: bool) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some(last) = self.last_soc {
+            if soc > last {
+                events.push(Event::Recharged { from: last });
+            }
+        }
+        if warning && !self.warning {
+            events.push(Event::Warning);
+        }
+
+        self.last_soc = Some(soc);
+        self.warning = warning;
+        events
+    }
+}
+
+/// Watch procedure
+///
+/// Keep sessions in memory, errors of a poll are reported and skipped.
+pub fn watch(conf: &Config, interval: Duration, times: Option<u32>, notify: bool, verbose: bool) {
+    let endpoints = conf.endpoints.clone().unwrap_or_default();
+    let mut accounts: Vec<(Account, Option<Session>, Watcher)> = conf
+        .accounts()
+        .into_iter()
+        .map(|a| {
+            let session = crate::read_session_cache(&a, verbose);
+            (a, session, Watcher::default())
+        })
+        .collect();
+    let mut polled = 0;
+
+    loop {
+        for (account, session, watcher) in accounts.iter_mut() {
+            poll(conf, account, session, watcher, &endpoints, notify, verbose);
+        }
+
+        polled += 1;
+        if times.is_some_and(|t| polled >= t) {
+            break;
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Poll electricity of the account once
+fn poll(
+    conf: &Config,
+    account: &Account,
+    session: &mut Option<Session>,
+    watcher: &mut Watcher,
+    endpoints: &Endpoints,
+    notify: bool,
+    verbose: bool,
+) {
+    let (result, ses) = match crate::query_ele(
+        &account.uid,
+        session.take(),
+        account.room.as_deref(),
+        endpoints,
+        verbose,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Account {} query electricity error: {}", account.label(), e);
+            return;
+        }
+    };
+    crate::cache_session(account, &ses, verbose);
+    session.replace(ses);
+
+    let forecast = match &conf.history_file {
+        Some(history_file) => crate::record_history(history_file, &result, verbose),
+        None => None,
+    };
+
+    let warning = match &account.server_chan {
+        Some(sc) => sc.is_warning(result.soc, forecast.as_ref()),
+        None => false,
+    };
+    println!(
+        "[{}] {} {}: {} kW·h",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        account.label(),
+        result.display_room_name,
+        result.soc
+    );
+
+    for event in watcher.update(result.soc, warning) {
+        let (title, desp) = match (&event, &account.server_chan) {
+            (Event::Warning, Some(sc)) => (
+                format!("{}{}", sc.warning_title, result.soc),
+                crate::fmt_ele_md(&result, forecast.as_ref()),
+            ),
+            (Event::Recharged { from }, sc) => (
+                format!(
+                    "{}{}",
+                    sc.as_ref().map_or("Recharged: ", |v| &v.title),
+                    result.soc
+                ),
+                format!(
+                    "Recharge detected: {} -> {} kW·h\n\n{}",
+                    from,
+                    result.soc,
+                    crate::fmt_ele_md(&result, forecast.as_ref())
+                ),
+            ),
+            (Event::Warning, None) => continue,
+        };
+        println!("{:?}: {}", event, title);
+
+        if let (true, Some(sc)) = (notify, &account.server_chan) {
+            if let Err(e) = yxy::req::notice::push_message(endpoints, &sc.key, &title, &desp) {
+                eprintln!("Fail to push message: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interval() {
+        assert_eq!(parse_interval("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_interval("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("1x").is_err());
+        assert!(parse_interval("m").is_err());
+        assert!(parse_interval("0h0m").is_err());
+        // Overflow
+        assert!(parse_interval("99999999999999999d").is_err());
+        assert!(parse_interval("18446744073709551615s1").is_err());
+    }
+
+    #[test]
+    fn transitions() {
+        let mut watcher = Watcher::default();
+        assert_eq!(watcher.update(20.0, false), vec![]);
+        assert_eq!(watcher.update(9.0, true), vec![Event::Warning]);
+        // Stay in warning state
+        assert_eq!(watcher.update(8.0, true), vec![]);
+        assert_eq!(
+            watcher.update(50.0, false),
+            vec![Event::Recharged { from: 8.0 }]
+        );
+    }
+}
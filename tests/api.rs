@@ -0,0 +1,474 @@
+//! Library APIs against the mock platform
+mod common;
+
+use common::MockServer;
+use yxy::error::Error;
+use yxy::req::{
+    self,
+    app::{RechargeInfo, RoomInfo},
+    login::LoginHandler,
+    Handler,
+};
+use yxy::session::Session;
+
+#[test]
+fn authorize() -> Result<(), Error> {
+    let server = MockServer::start();
+    let endpoints = server.endpoints();
+    let client = req::init_default_client()?;
+
+    let code = req::auth::get_oauth_code(&client, &endpoints, common::UID)?;
+    assert_eq!(code.len(), 32);
+
+    let (session, user) = req::auth::authorize(&client, &endpoints, &code)?;
+    assert_eq!(user.id, common::UID);
+    assert!(server.state().sessions.contains(&session));
+
+    // The code is consumed
+    assert!(req::auth::authorize(&client, &endpoints, &code).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn authorize_unknown_uid() -> Result<(), Error> {
+    let server = MockServer::start();
+    let client = req::init_default_client()?;
+
+    let result = req::auth::get_oauth_code(&client, &server.endpoints(), "unknown");
+    assert!(matches!(result, Err(Error::Runtime(_))));
+
+    Ok(())
+}
+
+#[test]
+fn query_electricity() -> Result<(), Error> {
+    let server = MockServer::start();
+    let (session, _) = yxy::auth_with_endpoints(common::UID, &server.endpoints())?;
+
+    let handler = Handler::with_endpoints(&session, server.endpoints())?;
+    let bind = handler.query_bind()?;
+    assert_eq!(bind.room_code, "R301");
+
+    let info = handler.query_electricity(RoomInfo {
+        area_id: &bind.area_id,
+        building_code: &bind.building_code,
+        floor_code: &bind.floor_code,
+        room_code: &bind.room_code,
+    })?;
+    assert_eq!(info.soc, 52.3);
+    assert_eq!(info.surplus_list.len(), 1);
+
+    let info = yxy::query_ele_with_endpoints(&session, server.endpoints())?;
+    assert_eq!(info.display_room_name, "East Building 1 301");
+
+    Ok(())
+}
+
+#[test]
+fn query_binds() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = Handler::with_endpoints(&server.session(), server.endpoints())?;
+
+    let binds = handler.query_binds()?;
+    let rooms: Vec<&str> = binds.iter().map(|b| b.room_code.as_str()).collect();
+    assert_eq!(rooms, ["R102", "R301"]);
+    assert_eq!(binds[0].building_name, "Building 2");
+
+    let info = handler.query_electricity(binds[0].room_info())?;
+    assert_eq!(info.display_room_name, "East Building 2 102");
+
+    Ok(())
+}
+
+#[test]
+fn browse_rooms() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = Handler::with_endpoints(&server.session(), server.endpoints())?;
+
+    let areas = handler.query_areas()?;
+    assert_eq!(areas.len(), 2);
+    assert_eq!(areas[1].area_name, "West");
+
+    let buildings = handler.query_buildings("1")?;
+    assert_eq!(buildings.len(), 2);
+    let floors = handler.query_floors("1", "B01")?;
+    assert_eq!(floors[0].floor_code, "F03");
+    let rooms = handler.query_rooms("1", "B01", "F03")?;
+    let codes: Vec<&str> = rooms.iter().map(|r| r.room_code.as_str()).collect();
+    assert_eq!(codes, ["R301", "R302"]);
+
+    // Not bound room
+    let info = handler.query_electricity(RoomInfo {
+        area_id: "1",
+        building_code: "B01",
+        floor_code: "F03",
+        room_code: "R302",
+    })?;
+    assert_eq!(info.display_room_name, "East Building 1 302");
+
+    Ok(())
+}
+
+#[test]
+fn recharge() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = Handler::with_endpoints(&server.session(), server.endpoints())?;
+    let info = handler.query_electricity(handler.query_bind()?.room_info())?;
+
+    let top_up_type = &info.top_up_type_list[0];
+    assert!(RechargeInfo::new(&info, top_up_type, 0.0).is_err());
+    let order = handler.recharge(&RechargeInfo::new(&info, top_up_type, 10.0)?)?;
+    assert_eq!(order.amount, "10.00");
+
+    let recharges = server.state().recharges.clone();
+    assert_eq!(recharges.len(), 1);
+    assert_eq!(recharges[0]["roomCode"], "R301");
+    assert_eq!(recharges[0]["cztype"], "1");
+
+    let info = handler.query_electricity(handler.query_bind()?.room_info())?;
+    assert_eq!(info.soc, 52.3 + 10.0 / common::PRICE);
+
+    Ok(())
+}
+
+#[test]
+fn api_error() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = Handler::with_endpoints(&server.session(), server.endpoints())?;
+
+    let result = handler.query_electricity(RoomInfo {
+        area_id: "1",
+        building_code: "B01",
+        floor_code: "F03",
+        room_code: "R404",
+    });
+    match result {
+        Err(Error::Api {
+            endpoint,
+            status_code,
+            message,
+            raw_body,
+        }) => {
+            assert_eq!(endpoint, req::url::application::QUERY_ELECTRICITY);
+            assert_eq!(status_code, 500);
+            assert_eq!(message, "房间不存在");
+            assert!(raw_body.contains("房间不存在"));
+        }
+        v => panic!("{:?}", v),
+    }
+
+    // Bad HTTP status
+    let mut endpoints = server.endpoints();
+    endpoints.application.push_str("/missing");
+    let handler = Handler::with_endpoints(&server.session(), endpoints)?;
+    assert!(matches!(
+        handler.query_bind(),
+        Err(Error::Api {
+            status_code: 404,
+            ..
+        })
+    ));
+
+    // Chained source
+    let e = Error::from(std::io::Error::other("io"));
+    assert!(std::error::Error::source(&e).is_some());
+
+    Ok(())
+}
+
+#[test]
+fn auth_expired() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = Handler::with_endpoints(&server.session(), server.endpoints())?;
+    handler.query_bind()?;
+
+    server.state().expire_sessions();
+    assert!(matches!(handler.query_bind(), Err(Error::AuthExpired)));
+
+    Ok(())
+}
+
+#[test]
+fn login_status_204() -> Result<(), Error> {
+    let server = MockServer::start();
+    let mut handler = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+    handler.token = Some("token".into());
+    let token = handler.get_security_token()?;
+
+    // Not a session expiry out of the application queries
+    server.state().app_failure = Some(204);
+    let results = [
+        handler.get_security_token().map(|_| ()),
+        handler.get_captcha_image(&token.security_token).map(|_| ()),
+        handler
+            .send_verification_code(&token.security_token, None)
+            .map(|_| ()),
+        handler.do_login(common::SMS_CODE).map(|_| ()),
+        handler.do_silent_login().map(|_| ()),
+        handler.do_login_by_password(common::PASSWORD).map(|_| ()),
+    ];
+    for result in results {
+        assert!(
+            matches!(
+                result,
+                Err(Error::Api {
+                    status_code: 204,
+                    ..
+                })
+            ),
+            "{:?}",
+            result
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn session_refresh() -> Result<(), Error> {
+    let server = MockServer::start();
+    let endpoints = server.endpoints();
+    let mut session = Session::auth(common::UID, &endpoints)?;
+    assert_eq!(session.user.as_ref().unwrap().id, common::UID);
+
+    server.state().expire_sessions();
+    let old = session.id.clone();
+    session.refresh(&endpoints)?;
+    assert_ne!(session.id, old);
+    session.handler(endpoints)?.query_bind()?;
+
+    Ok(())
+}
+
+#[test]
+fn authenticated_client() -> Result<(), Error> {
+    use std::sync::{Arc, Mutex};
+    use yxy::client::AuthenticatedClient;
+
+    let server = MockServer::start();
+    let refreshed = Arc::new(Mutex::new(Vec::new()));
+    let sessions = refreshed.clone();
+    let mut client = AuthenticatedClient::with_session(
+        Session::from_id(common::UID, "expired-session"),
+        server.endpoints(),
+    )?
+    .on_refresh(move |s| sessions.lock().unwrap().push(s.id.clone()));
+
+    // Reauthorized on expiry
+    let info = client.query_ele()?;
+    assert_eq!(info.soc, 52.3);
+    assert_eq!(refreshed.lock().unwrap().len(), 1);
+    assert_eq!(refreshed.lock().unwrap()[0], client.session().id);
+    assert!(client.session().last_used > 0);
+
+    // Session kept while valid
+    client.query_binds()?;
+    assert_eq!(refreshed.lock().unwrap().len(), 1);
+
+    server.state().expire_sessions();
+    client.query_bind()?;
+    assert_eq!(refreshed.lock().unwrap().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn sms_login() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+
+    let token = handler.get_security_token()?;
+    assert_eq!(token.level, 0);
+    assert!(handler.send_verification_code(&token.security_token, None)?);
+
+    assert!(matches!(
+        handler.do_login("000000"),
+        Err(Error::BadVerificationCode)
+    ));
+
+    let result = handler.do_login(common::SMS_CODE)?;
+    assert_eq!(result.id, common::UID);
+    assert_eq!(result.device_id, handler.device_id);
+
+    // Saved profile
+    let path = common::temp_dir().join("profile.json");
+    let path = path.to_str().unwrap();
+    assert_eq!(req::login::LoginResponse::load(path)?, None);
+    result.save(path)?;
+    let profile = req::login::LoginResponse::load(path)?.unwrap();
+    assert_eq!(profile, result);
+
+    let handler = LoginHandler::from_profile(&profile, server.endpoints())?;
+    assert_eq!(handler.phone_num, common::PHONE);
+    assert_eq!(handler.device_id, result.device_id);
+    assert_eq!(handler.token.as_deref(), Some(result.token.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn password_login() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+
+    assert_eq!(handler.get_public_key()?, common::PUBLIC_KEY);
+    assert!(matches!(
+        handler.do_login_by_password("wrong"),
+        Err(Error::BadPassword)
+    ));
+
+    let result = handler.do_login_by_password(common::PASSWORD)?;
+    assert_eq!(result.id, common::UID);
+    assert_eq!(result.device_id, handler.device_id);
+
+    Ok(())
+}
+
+#[test]
+fn silent_login() -> Result<(), Error> {
+    let server = MockServer::start();
+    let handler = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+    let token = handler.get_security_token()?;
+    handler.send_verification_code(&token.security_token, None)?;
+    let profile = handler.do_login(common::SMS_CODE)?;
+
+    let handler = LoginHandler::from_profile(&profile, server.endpoints())?;
+    let result = handler.do_silent_login()?;
+    assert_eq!(result.id, common::UID);
+    assert_eq!(result.token, profile.token);
+
+    // Token of another device
+    let other = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+    assert!(other.do_silent_login().is_err());
+
+    server.state().revoke_tokens();
+    assert!(matches!(
+        handler.do_silent_login(),
+        Err(Error::TokenRevoked)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn sms_login_captcha() -> Result<(), Error> {
+    let server = MockServer::start();
+    server.state().captcha_level = 1;
+    let handler = LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+
+    let token = handler.get_security_token()?;
+    assert_eq!(token.level, 1);
+    assert_eq!(
+        handler.get_captcha_image(&token.security_token)?,
+        common::CAPTCHA_IMAGE
+    );
+    let image = handler.get_captcha(&token.security_token)?;
+    assert_eq!(image.format, req::login::ImageFormat::Png);
+    assert!(image.bytes.starts_with(b"\x89PNG"));
+
+    assert!(matches!(
+        handler.send_verification_code(&token.security_token, None),
+        Err(Error::Api {
+            status_code: 203,
+            ..
+        })
+    ));
+    assert!(handler.send_verification_code(&token.security_token, Some(common::CAPTCHA))?);
+
+    // Solved by the hook
+    let handler = handler.with_captcha_solver(|image: &req::login::CaptchaImage| {
+        assert_eq!(image.format, req::login::ImageFormat::Png);
+        Ok(common::CAPTCHA.to_string())
+    });
+    assert!(handler.request_verification_code()?);
+
+    Ok(())
+}
+
+#[test]
+fn sms_rejected() -> Result<(), Error> {
+    let server = MockServer::start();
+    for (phone, expected) in [
+        (common::BAD_PHONE, Error::BadPhoneNumber),
+        (common::LIMITED_PHONE, Error::VerificationLimit),
+    ] {
+        let handler = LoginHandler::init_with_endpoints(
+            phone,
+            &req::login::gen_device_id(),
+            server.endpoints(),
+        )?;
+        let token = handler.get_security_token()?;
+        let result = handler.send_verification_code(&token.security_token, None);
+        assert_eq!(
+            std::mem::discriminant(&result.unwrap_err()),
+            std::mem::discriminant(&expected)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn push_message() -> Result<(), Error> {
+    let server = MockServer::start();
+
+    let (_, readkey) = req::notice::push_message(&server.endpoints(), "key", "Title", "Desp")?;
+    assert_eq!(readkey, "mock-readkey");
+    assert_eq!(
+        server.state().pushes,
+        vec![("Title".to_string(), "Desp".to_string())]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_query_electricity() -> Result<(), Error> {
+    use req::r#async;
+
+    let server = MockServer::start();
+    let (session, _) = r#async::auth_with_endpoints(common::UID, &server.endpoints()).await?;
+    let info = r#async::query_ele_with_endpoints(&session, server.endpoints()).await?;
+    assert_eq!(info.soc, 52.3);
+
+    let handler = r#async::login::LoginHandler::init_with_endpoints(
+        common::PHONE,
+        &req::login::gen_device_id(),
+        server.endpoints(),
+    )?;
+    let token = handler.get_security_token().await?;
+    assert!(
+        handler
+            .send_verification_code(&token.security_token, None)
+            .await?
+    );
+    assert_eq!(handler.do_login(common::SMS_CODE).await?.id, common::UID);
+
+    Ok(())
+}
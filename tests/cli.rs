@@ -0,0 +1,770 @@
+//! Command line program against the mock platform
+mod common;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use common::MockServer;
+use yxy::session::Session;
+
+/// Write `conf.yaml` pointing to the mock server
+fn write_conf(dir: &Path, server: &MockServer, extra: &str) -> PathBuf {
+    let path = dir.join("conf.yaml");
+    std::fs::write(
+        &path,
+        format!(
+            "uid: \"{}\"\ncookie_file: \"{}\"\nendpoints:\n  auth: \"{url}\"\n  application: \"{url}\"\n  app: \"{url}\"\n  server_chan: \"{url}\"\n{}",
+            common::UID,
+            dir.join("cookie.tmp").display(),
+            extra,
+            url = server.url(),
+        ),
+    )
+    .unwrap();
+    path
+}
+
+/// Write `conf.yaml` without uid, saving the login profile & state into `dir`
+fn write_login_conf(dir: &Path, server: &MockServer, extra: &str) -> PathBuf {
+    let path = dir.join("conf.yaml");
+    std::fs::write(
+        &path,
+        format!(
+            "profile_file: \"{}\"\nlogin_state_file: \"{}\"\nendpoints:\n  auth: \"{url}\"\n  application: \"{url}\"\n  app: \"{url}\"\n  server_chan: \"{url}\"\n{}",
+            dir.join("profile.json").display(),
+            dir.join("login_state.json").display(),
+            extra,
+            url = server.url(),
+        ),
+    )
+    .unwrap();
+    path
+}
+
+/// Session cached in the file
+fn cached_session(path: &Path, uid: &str) -> Session {
+    Session::load(path.to_str().unwrap(), uid).unwrap().unwrap()
+}
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yxy"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn default_query() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+
+    let output = run(&["-c", conf.to_str().unwrap()], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Room: East Building 1 301"), "{}", stdout);
+    assert!(stdout.contains("Total Surplus: 52.3 kW·h"), "{}", stdout);
+
+    // Session cached
+    let session = cached_session(&dir.join("cookie.tmp"), common::UID);
+    assert!(server.state().sessions.contains(&session.id));
+    assert_eq!(session.user.unwrap().id, common::UID);
+    assert!(session.last_used >= session.created_at);
+}
+
+#[test]
+fn reauthorize_expired_session() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+    std::fs::write(dir.join("cookie.tmp"), "expired-session").unwrap();
+
+    run(&["-c", conf.to_str().unwrap()], "");
+
+    // Legacy cache of bare session id
+    let session = cached_session(&dir.join("cookie.tmp"), common::UID);
+    assert_ne!(session.id, "expired-session");
+    assert!(server.state().sessions.contains(&session.id));
+}
+
+#[test]
+fn notify_warning() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(
+        &dir,
+        &server,
+        "server_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_threshold: 60.0\n  warning_title: \"Warning: \"\n  log_level: 1\n",
+    );
+
+    run(&["-n", "-c", conf.to_str().unwrap()], "");
+
+    let pushes = server.state().pushes.clone();
+    assert_eq!(pushes.len(), 1);
+    assert_eq!(pushes[0].0, "Warning: 52.3");
+    assert!(pushes[0].1.contains("# Electricity Info"));
+}
+
+#[test]
+fn query_uid() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+
+    let output = run(
+        &["-c", conf.to_str().unwrap(), "query", "uid", common::PHONE],
+        &format!("{}\n", common::SMS_CODE),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("UID: {}", common::UID)),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn query_uid_by_password() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+
+    let output = run(
+        &[
+            "-c",
+            conf.to_str().unwrap(),
+            "query",
+            "uid",
+            common::PHONE,
+            "--password",
+        ],
+        &format!("{}\n", common::PASSWORD),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("UID: {}", common::UID)),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn login_profile() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let profile_file = dir.join("profile.json");
+    let conf = write_login_conf(&dir, &server, "");
+    let conf = conf.to_str().unwrap();
+
+    let output = run(
+        &["-c", conf, "login", common::PHONE],
+        &format!("{}\n", common::SMS_CODE),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("profile saved. UID: {}", common::UID)),
+        "{}",
+        stdout
+    );
+    let profile = yxy::req::login::LoginResponse::load(profile_file.to_str().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(profile.id, common::UID);
+
+    // Silent login by the saved token
+    let output = run(&["-c", conf, "login"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Login silently"), "{}", stdout);
+
+    // Token revoked, device id of the phone reused
+    server.state().revoke_tokens();
+    let output = run(
+        &["-c", conf, "login", common::PHONE],
+        &format!("{}\n", common::SMS_CODE),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Login successfully"), "{}", stdout);
+    let relogin = yxy::req::login::LoginResponse::load(profile_file.to_str().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(relogin.device_id, profile.device_id);
+    assert_ne!(relogin.token, profile.token);
+
+    // Uid of the profile
+    let output = run(&["-c", conf], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Total Surplus: 52.3 kW·h"), "{}", stdout);
+}
+
+#[test]
+fn login_two_phase() {
+    let server = MockServer::start();
+    server.state().captcha_level = 1;
+    let dir = common::temp_dir();
+    let state_file = dir.join("login_state.json");
+    let conf = write_login_conf(&dir, &server, "");
+    let conf = conf.to_str().unwrap();
+
+    // Stop at the image captcha
+    let output = run(&["-c", conf, "login", "start", common::PHONE], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let captcha = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Captcha: "))
+        .unwrap();
+    assert!(captcha.ends_with(".png"), "{}", stdout);
+    assert!(std::fs::read(captcha).unwrap().starts_with(b"\x89PNG"));
+    let state = yxy::req::login::LoginState::load(state_file.to_str().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(state.phone_num, common::PHONE);
+
+    let output = run(
+        &[
+            "-c",
+            conf,
+            "login",
+            "start",
+            common::PHONE,
+            "--captcha",
+            common::CAPTCHA,
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("yxy login finish"), "{}", stdout);
+
+    // Code arrives later, in another process
+    let output = run(&["-c", conf, "login", "finish", common::SMS_CODE], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("profile saved. UID: {}", common::UID)),
+        "{}",
+        stdout
+    );
+    assert!(!state_file.exists());
+    let profile = yxy::req::login::LoginResponse::load(dir.join("profile.json").to_str().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(profile.device_id, state.device_id);
+}
+
+#[cfg(unix)]
+#[test]
+fn login_captcha_command() {
+    let server = MockServer::start();
+    server.state().captcha_level = 1;
+    let dir = common::temp_dir();
+    let conf = write_login_conf(
+        &dir,
+        &server,
+        &format!(
+            "captcha_command: [\"sh\", \"-c\", \"cat > /dev/null; echo {}\"]\n",
+            common::CAPTCHA
+        ),
+    );
+    let conf = conf.to_str().unwrap();
+
+    // No captcha from stdin
+    let output = run(
+        &["-c", conf, "login", common::PHONE],
+        &format!("{}\n", common::SMS_CODE),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("profile saved"), "{}", stdout);
+
+    let output = run(&["-c", conf, "login", "start", common::PHONE], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("yxy login finish"), "{}", stdout);
+}
+
+#[test]
+fn query_ele_by_uid() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+
+    let output = run(
+        &["-c", conf.to_str().unwrap(), "query", "ele", common::UID],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Total Surplus: 52.3 kW·h"), "{}", stdout);
+}
+
+#[test]
+fn query_binds() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+
+    let output = run(
+        &["-c", conf.to_str().unwrap(), "query", "bind", common::UID],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1-B02-F01-R102"), "{}", stdout);
+    assert!(stdout.contains("1-B01-F03-R301"), "{}", stdout);
+    assert!(stdout.contains("Building 2"), "{}", stdout);
+
+    // Choose the room
+    let output = run(
+        &[
+            "-c",
+            conf.to_str().unwrap(),
+            "query",
+            "ele",
+            common::UID,
+            "--room",
+            "1-B02-F01-R102",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Room: East Building 2 102"), "{}", stdout);
+
+    // Room of config
+    let conf = write_conf(&dir, &server, "room: \"1-B02-F01-R102\"\n");
+    let output = run(&["-c", conf.to_str().unwrap()], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Room: East Building 2 102"), "{}", stdout);
+}
+
+#[test]
+fn query_room() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+    let conf = conf.to_str().unwrap();
+
+    // List the next level
+    let output = run(&["-c", conf, "query", "room", common::UID], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("West"), "{}", stdout);
+
+    let output = run(
+        &[
+            "-c",
+            conf,
+            "query",
+            "room",
+            common::UID,
+            "--area",
+            "1",
+            "--building",
+            "B01",
+            "--floor",
+            "F03",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("R302"), "{}", stdout);
+
+    let output = run(
+        &[
+            "-c",
+            conf,
+            "query",
+            "room",
+            common::UID,
+            "--area",
+            "1",
+            "--building",
+            "B01",
+            "--floor",
+            "F03",
+            "--room",
+            "R302",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Room: East Building 1 302"), "{}", stdout);
+}
+
+#[test]
+fn query_room_missing_parent() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+    let conf = conf.to_str().unwrap();
+
+    for (args, message) in [
+        (&["--building", "B01"][..], "--building requires --area"),
+        (
+            &["--area", "1", "--floor", "F03"],
+            "--floor requires --building",
+        ),
+        (&["--room", "R302"], "--room requires --floor"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_yxy"))
+            .args(["-c", conf, "query", "room", common::UID])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+}
+
+#[test]
+fn recharge() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(&dir, &server, "");
+    let conf = conf.to_str().unwrap();
+
+    // Nothing submitted without opt-in
+    let output = Command::new(env!("CARGO_BIN_EXE_yxy"))
+        .args(["-c", conf, "recharge", "--amount", "5"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("allow_unverified_recharge"), "{}", stderr);
+    assert!(server.state().recharges.is_empty());
+
+    let conf = write_conf(&dir, &server, "allow_unverified_recharge: true\n");
+    let conf = conf.to_str().unwrap();
+
+    // Nothing submitted on dry run
+    let output = run(&["-c", conf, "recharge", "--amount", "5", "--dry-run"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"amount\": \"5.00\""), "{}", stdout);
+    assert!(stdout.contains("\"cztype\": \"1\""), "{}", stdout);
+    assert!(server.state().recharges.is_empty());
+
+    let output = run(&["-c", conf, "recharge", "--amount", "5", "-t", "照明"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("order no: mock-order-"), "{}", stdout);
+    assert_eq!(server.state().recharges.len(), 1);
+}
+
+#[test]
+fn auto_recharge() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let ledger = dir.join("recharge.jsonl");
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "allow_unverified_recharge: true\nserver_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_title: \"Warning: \"\n  log_level: 1\nauto_recharge:\n  threshold: 60.0\n  amount: 10.0\n  monthly_limit: 15.0\n  ledger_file: \"{}\"\n",
+            ledger.display()
+        ),
+    );
+    let conf = conf.to_str().unwrap();
+
+    let output = run(&["-c", conf], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(server.state().recharges.len(), 1);
+    // Refreshed after recharge
+    assert!(stdout.contains("Total Surplus: 72.3 kW·h"), "{}", stdout);
+    assert_eq!(server.state().pushes[0].0, "Auto recharged: ￥10");
+    assert_eq!(std::fs::read_to_string(&ledger).unwrap().lines().count(), 1);
+    assert!(!dir.join("recharge.jsonl.lock").exists());
+
+    // Monthly limit reached
+    server.state().soc = 52.3;
+    run(&["-c", conf], "");
+    assert_eq!(server.state().recharges.len(), 1);
+    assert_eq!(server.state().pushes[1].0, "Auto recharge refused: 52.3");
+    assert_eq!(std::fs::read_to_string(&ledger).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn auto_recharge_opt_in() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "server_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_title: \"Warning: \"\n  log_level: 1\nauto_recharge:\n  threshold: 60.0\n  amount: 10.0\n  monthly_limit: 15.0\n  ledger_file: \"{}\"\n",
+            dir.join("recharge.jsonl").display()
+        ),
+    );
+
+    run(&["-c", conf.to_str().unwrap()], "");
+    assert!(server.state().recharges.is_empty());
+    assert_eq!(server.state().pushes[0].0, "Auto recharge failed: 52.3");
+    assert!(server.state().pushes[0]
+        .1
+        .contains("allow_unverified_recharge"));
+}
+
+#[test]
+fn auto_recharge_unwritable_ledger() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    // Parent directory missing, not writable
+    let ledger = dir.join("missing").join("recharge.jsonl");
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "allow_unverified_recharge: true\nserver_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_title: \"Warning: \"\n  log_level: 1\nauto_recharge:\n  threshold: 60.0\n  amount: 10.0\n  monthly_limit: 15.0\n  ledger_file: \"{}\"\n",
+            ledger.display()
+        ),
+    );
+    let conf = conf.to_str().unwrap();
+
+    run(&["-c", conf], "");
+    // Never paid without a writable ledger
+    assert!(server.state().recharges.is_empty());
+    assert_eq!(server.state().pushes[0].0, "Auto recharge failed: 52.3");
+}
+
+#[test]
+fn auto_recharge_lock() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let ledger = dir.join("recharge.jsonl");
+    let lock = dir.join("recharge.jsonl.lock");
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "allow_unverified_recharge: true\nauto_recharge:\n  threshold: 60.0\n  amount: 10.0\n  monthly_limit: 15.0\n  ledger_file: \"{}\"\n",
+            ledger.display()
+        ),
+    );
+    let conf = conf.to_str().unwrap();
+    let entry = |order_no: &str| {
+        format!(
+            r#"{{"time":{},"uid":"{}","room":"1-B01-F03-R301","amount":10.0,"order_no":"{}"}}"#,
+            chrono::Local::now().timestamp(),
+            common::UID,
+            order_no
+        )
+    };
+
+    // Unconfirmed recharge of a previous run
+    std::fs::write(&lock, entry("")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_yxy"))
+        .args(["-c", conf])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unconfirmed recharge"));
+    assert!(server.state().recharges.is_empty());
+
+    // Paid but not recorded, counted against the monthly limit
+    std::fs::write(&lock, entry("order")).unwrap();
+    run(&["-c", conf], "");
+    assert!(server.state().recharges.is_empty());
+    assert!(!lock.exists());
+    assert_eq!(std::fs::read_to_string(&ledger).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn encrypted_store() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let store_file = dir.join("secrets.enc");
+    let conf = dir.join("conf.yaml");
+    std::fs::write(
+        &conf,
+        format!(
+            "store_file: \"{}\"\nendpoints:\n  auth: \"{url}\"\n  application: \"{url}\"\n  app: \"{url}\"\n  server_chan: \"{url}\"\n",
+            store_file.display(),
+            url = server.url(),
+        ),
+    )
+    .unwrap();
+    let conf = conf.to_str().unwrap();
+
+    // Passphrase from stdin
+    run(&["-c", conf, "store", "--uid", common::UID], "passphrase\n");
+    assert!(!std::fs::read_to_string(&store_file)
+        .unwrap()
+        .contains(common::UID));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&store_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let output = run(&["-c", conf], "passphrase\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Total Surplus: 52.3 kW·h"), "{}", stdout);
+
+    // Session cached into the store
+    let secrets = yxy::store::Store::new(&store_file)
+        .load("passphrase")
+        .unwrap();
+    assert_eq!(secrets.uid.as_deref(), Some(common::UID));
+    let session = &secrets.sessions[common::UID];
+    assert!(server.state().sessions.contains(&session.id));
+
+    // Wrong passphrase
+    let output = Command::new(env!("CARGO_BIN_EXE_yxy"))
+        .args(["-c", conf])
+        .env("YXY_PASSPHRASE", "wrong")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn history() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "history_file: \"{}\"\n",
+            dir.join("history.jsonl").display()
+        ),
+    );
+    let conf = conf.to_str().unwrap();
+
+    run(&["-c", conf], "");
+    server.state().soc = 50.3;
+    run(&["-c", conf], "");
+
+    let records = yxy::history::History::new(dir.join("history.jsonl"))
+        .load(None)
+        .unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].room, "1-B01-F03-R301");
+
+    let output = run(&["-c", conf, "history"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Records: 2"), "{}", stdout);
+    assert!(stdout.contains("2.00 kW·h"), "{}", stdout);
+}
+
+#[test]
+fn notify_forecast_warning() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let history_file = dir.join("history.jsonl");
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "history_file: \"{}\"\nserver_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_days: 7.0\n  warning_title: \"Warning: \"\n  log_level: 1\n",
+            history_file.display()
+        ),
+    );
+
+    // 10 kW·h per day consumed before
+    let now = chrono::Local::now().timestamp();
+    let history = yxy::history::History::new(&history_file);
+    for (days_ago, soc) in [(2, 72.3), (1, 62.3)] {
+        history
+            .append(&yxy::history::Record {
+                time: now - days_ago * 86400,
+                room: "1-B01-F03-R301".into(),
+                room_name: "301".into(),
+                soc,
+                total_soc_amount: soc * 0.5,
+                surplus: Vec::new(),
+            })
+            .unwrap();
+    }
+
+    run(&["-n", "-c", conf.to_str().unwrap()], "");
+
+    let pushes = server.state().pushes.clone();
+    assert_eq!(pushes.len(), 1);
+    assert_eq!(pushes[0].0, "Warning: 52.3");
+    assert!(
+        pushes[0].1.contains("- Remaining: **5.2 days"),
+        "{}",
+        pushes[0].1
+    );
+}
+
+#[test]
+fn watch_notify_on_transition() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(
+        &dir,
+        &server,
+        "server_chan:\n  key: key\n  title: \"Surplus: \"\n  warning_threshold: 60.0\n  warning_title: \"Warning: \"\n  log_level: 0\n",
+    );
+
+    let output = run(
+        &[
+            "-n",
+            "-c",
+            conf.to_str().unwrap(),
+            "watch",
+            "--interval",
+            "1s",
+            "--times",
+            "2",
+        ],
+        "",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("52.3 kW·h").count(), 2, "{}", stdout);
+
+    // Notify once on turning into warning state
+    let state = server.state();
+    assert_eq!(state.pushes.len(), 1);
+    assert_eq!(state.pushes[0].0, "Warning: 52.3");
+
+    // Authorize once, session kept in memory
+    let authorized = state
+        .requests
+        .iter()
+        .filter(|p| p.as_str() == yxy::req::url::application::GET_USER_FOR_AUTHORIZE)
+        .count();
+    assert_eq!(authorized, 1);
+}
+
+#[test]
+fn multiple_accounts() {
+    let server = MockServer::start();
+    let dir = common::temp_dir();
+    let conf = write_conf(
+        &dir,
+        &server,
+        &format!(
+            "accounts:\n  - name: Second\n    uid: \"{}\"\n    cookie_file: \"{}\"\n  - name: Unknown\n    uid: unknown\n",
+            common::UID_2,
+            dir.join("cookie2.tmp").display()
+        ),
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_yxy"))
+        .args(["-c", conf.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The failed account does not stop others, but fails the run
+    assert!(!output.status.success());
+    assert_eq!(stdout.matches("Total Surplus: 52.3 kW·h").count(), 2);
+    assert!(stdout.contains("Account: Second"), "{}", stdout);
+    assert!(stdout.contains(common::UID), "{}", stdout);
+    assert!(stdout.contains("Unknown           Error:"), "{}", stdout);
+
+    // Sessions cached separately
+    let first = cached_session(&dir.join("cookie.tmp"), common::UID);
+    let second = cached_session(&dir.join("cookie2.tmp"), common::UID_2);
+    assert_ne!(first.id, second.id);
+}
@@ -0,0 +1,163 @@
+//! JSON C Bind against the mock platform
+mod common;
+
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+use common::MockServer;
+use serde_json::{json, Value};
+use yxy::ffi;
+
+/// Call `yxy_call` and parse the JSON result
+fn call(method: &str, args: Option<Value>) -> Value {
+    let method = CString::new(method).unwrap();
+    let args = args.map(|v| CString::new(v.to_string()).unwrap());
+
+    let p = ffi::yxy_call(
+        method.as_ptr(),
+        args.as_ref().map_or(ptr::null(), |v| v.as_ptr()),
+    );
+    assert!(!p.is_null());
+    let resp = unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string();
+    ffi::free_c_string(p);
+
+    serde_json::from_str(&resp).unwrap()
+}
+
+/// Endpoints argument of the mock server
+fn endpoints(server: &MockServer) -> Value {
+    let url = server.url();
+    json!({ "auth": url, "application": url, "app": url, "serverChan": url })
+}
+
+#[test]
+fn json_query() {
+    let server = MockServer::start();
+
+    let resp = call(
+        "auth",
+        Some(json!({ "uid": common::UID, "endpoints": endpoints(&server) })),
+    );
+    assert_eq!(resp["code"], 0);
+    assert_eq!(resp["data"]["user"]["id"], common::UID);
+    let session = resp["data"]["session"].as_str().unwrap().to_string();
+
+    let args = json!({ "session": session, "endpoints": endpoints(&server) });
+    let resp = call("query_bind", Some(args.clone()));
+    assert_eq!(resp["code"], 0);
+    assert!(!resp["data"].as_array().unwrap().is_empty());
+    assert!(resp["data"][0]["roomCode"].is_string());
+
+    let resp = call("query_ele", Some(args));
+    assert_eq!(resp["code"], 0);
+    assert!(resp["data"]["soc"].is_number());
+    assert!(resp["data"]["surplusList"].is_array());
+    assert!(resp["data"]["topUpTypeList"].is_array());
+
+    // Same error codes as the C Binds
+    server.state().expire_sessions();
+    let resp = call(
+        "query_ele",
+        Some(json!({ "session": session, "endpoints": endpoints(&server) })),
+    );
+    assert_eq!(resp["code"], 201);
+    assert_eq!(ffi::yxy_last_error_code(), 201);
+}
+
+#[test]
+fn json_login() {
+    let server = MockServer::start();
+
+    let resp = call("gen_device_id", None);
+    let device_id = resp["data"].as_str().unwrap().to_string();
+    let args = |extra: Value| {
+        let mut args = json!({
+            "phoneNum": common::PHONE,
+            "deviceId": device_id,
+            "endpoints": endpoints(&server),
+        });
+        args.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        Some(args)
+    };
+
+    let resp = call("get_security_token", args(json!({})));
+    assert_eq!(resp["data"]["level"], 0);
+    let security_token = resp["data"]["securityToken"].clone();
+
+    let resp = call(
+        "send_verification_code",
+        args(json!({ "securityToken": security_token })),
+    );
+    assert_eq!(resp, json!({ "code": 0, "data": true }));
+
+    let resp = call("do_login", args(json!({ "code": "000000" })));
+    assert_eq!(resp["code"], 206);
+
+    let resp = call("do_login", args(json!({ "code": common::SMS_CODE })));
+    assert_eq!(resp["code"], 0);
+    assert_eq!(resp["data"]["id"], common::UID);
+    assert_eq!(resp["data"]["deviceId"], device_id.as_str());
+    let token = resp["data"]["token"].clone();
+
+    let resp = call("do_silent_login", args(json!({ "token": token })));
+    assert_eq!(resp["data"]["id"], common::UID);
+
+    let resp = call(
+        "do_login_by_password",
+        args(json!({ "password": common::PASSWORD })),
+    );
+    assert_eq!(resp["data"]["id"], common::UID);
+
+    // Distinct codes of bad password and revoked token
+    let resp = call("do_login_by_password", args(json!({ "password": "wrong" })));
+    assert_eq!(resp["code"], 211);
+
+    server.state().revoke_tokens();
+    let resp = call("do_silent_login", args(json!({ "token": token })));
+    assert_eq!(resp["code"], 210);
+}
+
+#[test]
+fn json_bad_call() {
+    let resp = call("unknown", None);
+    assert_eq!(resp["code"], 208);
+    assert_eq!(resp["message"], "Unknown method: unknown");
+
+    let resp = call("query_ele", Some(json!({})));
+    assert_eq!(resp["code"], 208);
+    assert!(resp["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Bad arguments"));
+
+    let resp = call(
+        "do_login",
+        Some(json!({ "phoneNum": common::PHONE, "deviceId": "device" })),
+    );
+    assert_eq!(resp["code"], 208);
+    assert_eq!(resp["message"], "`code` is required");
+
+    // Keys of the config are not accepted, never fall back to production silently
+    let resp = call(
+        "query_ele",
+        Some(json!({ "session": "session", "endpoints": { "server_chan": "http://localhost" } })),
+    );
+    assert_eq!(resp["code"], 208);
+    assert!(resp["message"]
+        .as_str()
+        .unwrap()
+        .contains("unknown field `server_chan`"));
+
+    let resp = call(
+        "query_ele",
+        Some(json!({ "session": "session", "phoneNum": common::PHONE })),
+    );
+    assert_eq!(resp["code"], 208);
+
+    let p = ffi::yxy_call(ptr::null(), ptr::null());
+    let resp = unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string();
+    ffi::free_c_string(p);
+    assert_eq!(resp, r#"{"code":208,"message":"`method` is null"}"#);
+}